@@ -0,0 +1,136 @@
+use crate::models::ids::{BuildId, TaskId, VersionId};
+use crate::models::status::TaskStatus;
+use crate::models::task::EvgTask;
+use crate::{EvgApiClient, EvgError};
+use futures::stream::StreamExt;
+
+/// The outcome of an action applied to a whole build or version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionReport {
+    /// Whether the action was only simulated.
+    pub dry_run: bool,
+    /// Tasks changed by the action, or that would have been changed by a dry run.
+    pub affected_tasks: Vec<TaskId>,
+    /// Tasks the action was applied to one by one but failed for. They are left unchanged.
+    pub failed_tasks: Vec<TaskId>,
+}
+
+impl ActionReport {
+    pub(crate) fn new(dry_run: bool, affected_tasks: Vec<TaskId>) -> Self {
+        Self {
+            dry_run,
+            affected_tasks,
+            failed_tasks: vec![],
+        }
+    }
+
+    /// Check if the action was applied to every task it should have changed.
+    pub fn is_complete(&self) -> bool {
+        self.failed_tasks.is_empty()
+    }
+}
+
+/// Tasks that would be scheduled or unscheduled by activating or deactivating their build.
+pub(crate) fn is_activation_affected(task: &EvgTask, activated: bool) -> bool {
+    if activated {
+        !task.activated && !task.status.is_finished()
+    } else {
+        task.activated && task.status == TaskStatus::Undispatched
+    }
+}
+
+/// Tasks that are restarted when restarting a build or version.
+pub(crate) fn is_restart_affected(task: &EvgTask) -> bool {
+    task.status.is_failure()
+}
+
+/// Tasks that are stopped when aborting a build or version.
+pub(crate) fn is_abort_affected(task: &EvgTask) -> bool {
+    task.activated && task.status.is_in_progress()
+}
+
+/// Tasks whose priority changes when setting the priority of a build.
+pub(crate) fn is_priority_affected(task: &EvgTask) -> bool {
+    !task.status.is_finished()
+}
+
+/// Fetch all tasks of the given build.
+pub(crate) async fn build_tasks<C>(client: &C, build_id: &BuildId) -> Result<Vec<EvgTask>, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+{
+    let mut tasks = vec![];
+    let mut stream = client.stream_build_tasks(build_id, None);
+    while let Some(task) = stream.next().await {
+        tasks.push(task?);
+    }
+    Ok(tasks)
+}
+
+/// Fetch all tasks in all builds of the given version.
+pub(crate) async fn version_tasks<C>(
+    client: &C,
+    version_id: &VersionId,
+) -> Result<Vec<EvgTask>, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+{
+    let version = client.get_version(version_id).await?;
+    let mut tasks = vec![];
+    for build in version.build_variants_status.iter().flatten() {
+        tasks.extend(build_tasks(client, &build.build_id).await?);
+    }
+    Ok(tasks)
+}
+
+/// Find the tasks of the given build matching `affected`.
+pub(crate) async fn build_tasks_matching<C, F>(
+    client: &C,
+    build_id: &BuildId,
+    affected: F,
+) -> Result<Vec<TaskId>, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+    F: Fn(&EvgTask) -> bool,
+{
+    let tasks = build_tasks(client, build_id).await?;
+    Ok(matching_ids(tasks, affected))
+}
+
+/// Find the tasks in all builds of the given version matching `affected`.
+pub(crate) async fn version_tasks_matching<C, F>(
+    client: &C,
+    version_id: &VersionId,
+    affected: F,
+) -> Result<Vec<TaskId>, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+    F: Fn(&EvgTask) -> bool,
+{
+    let tasks = version_tasks(client, version_id).await?;
+    Ok(matching_ids(tasks, affected))
+}
+
+fn matching_ids<F: Fn(&EvgTask) -> bool>(tasks: Vec<EvgTask>, affected: F) -> Vec<TaskId> {
+    tasks
+        .into_iter()
+        .filter(|t| affected(t))
+        .map(|t| t.task_id)
+        .collect()
+}
+
+/// Restart each of the given tasks, carrying on after failures so the report lists every task
+/// that was or was not restarted.
+pub(crate) async fn restart_tasks<C>(client: &C, task_ids: Vec<TaskId>) -> ActionReport
+where
+    C: EvgApiClient + ?Sized,
+{
+    let mut report = ActionReport::new(false, vec![]);
+    for task_id in task_ids {
+        match client.restart_task(&task_id, false).await {
+            Ok(_) => report.affected_tasks.push(task_id),
+            Err(_) => report.failed_tasks.push(task_id),
+        }
+    }
+    report
+}
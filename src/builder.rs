@@ -0,0 +1,206 @@
+use crate::{in_flight_limit, EvgClient, EvgError, RateLimiter, RetryPolicy};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Proxy};
+use std::time::Duration;
+
+/// Builder for an `EvgClient` that does not depend on an evergreen config file.
+pub struct EvgClientBuilder {
+    api_server_host: String,
+    user: String,
+    api_key: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    proxy: Option<Proxy>,
+    root_certificates: Vec<Certificate>,
+    default_headers: HeaderMap,
+    client: Option<Client>,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    max_in_flight: Option<usize>,
+}
+
+impl EvgClientBuilder {
+    /// Create a builder for a client talking to the given evergreen server with the given
+    /// credentials.
+    pub fn new(api_server_host: &str, user: &str, api_key: &str) -> Self {
+        Self {
+            api_server_host: api_server_host.trim_end_matches('/').to_string(),
+            user: user.to_string(),
+            api_key: api_key.to_string(),
+            timeout: None,
+            connect_timeout: None,
+            user_agent: None,
+            proxy: None,
+            root_certificates: vec![],
+            default_headers: HeaderMap::new(),
+            client: None,
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            max_in_flight: None,
+        }
+    }
+
+    /// Timeout for each request, from sending it until the response body is read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for establishing a connection to the server.
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// User-Agent to send with each request.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Proxy to send requests through.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Additional root certificate to trust when connecting to the server.
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Additional header to send with each request.
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    /// Send requests with a pre-built reqwest client.
+    ///
+    /// The timeout, user agent, proxy and certificate settings of this builder are ignored when
+    /// a client is provided; configure them on the provided client instead.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Policy to use when retrying failed requests.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limit the rate at which requests are started.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Limit the number of requests that can be in flight at once. The limit must be at least 1.
+    ///
+    /// A request counts against the limit until its response headers arrive; response bodies,
+    /// such as streamed logs, are read outside of it.
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    /// Build the configured client.
+    pub fn build(self) -> Result<EvgClient, EvgError> {
+        let in_flight = self.max_in_flight.map(in_flight_limit).transpose()?;
+        let mut headers = self.default_headers;
+        headers.insert(
+            "Api-User",
+            HeaderValue::from_str(&self.user)
+                .map_err(|e| EvgError::config_with_source("invalid user", e))?,
+        );
+        headers.insert(
+            "Api-Key",
+            HeaderValue::from_str(&self.api_key)
+                .map_err(|e| EvgError::config_with_source("invalid api_key", e))?,
+        );
+
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = Client::builder();
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(connect_timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(connect_timeout);
+                }
+                if let Some(user_agent) = self.user_agent {
+                    builder = builder.user_agent(user_agent);
+                }
+                if let Some(proxy) = self.proxy {
+                    builder = builder.proxy(proxy);
+                }
+                for certificate in self.root_certificates {
+                    builder = builder.add_root_certificate(certificate);
+                }
+                builder.build()?
+            }
+        };
+
+        Ok(EvgClient {
+            api_server_host: self.api_server_host,
+            client,
+            headers,
+            retry_policy: self.retry_policy,
+            rate_limiter: self.rate_limiter,
+            in_flight,
+            cassette: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_should_send_credentials_with_each_request() {
+        let client = EvgClientBuilder::new("https://evergreen.example.com/", "user", "key")
+            .default_header(
+                HeaderName::from_static("x-extra"),
+                HeaderValue::from_static("value"),
+            )
+            .build()
+            .unwrap();
+
+        let request = client.get("https://evergreen.example.com").build().unwrap();
+
+        assert_eq!(request.headers()["Api-User"], "user");
+        assert_eq!(request.headers()["Api-Key"], "key");
+        assert_eq!(request.headers()["x-extra"], "value");
+        assert_eq!(
+            client.build_url("tasks", "task_id"),
+            "https://evergreen.example.com/rest/v2/tasks/task_id"
+        );
+    }
+
+    #[test]
+    fn test_build_should_reject_invalid_credentials() {
+        let result =
+            EvgClientBuilder::new("https://evergreen.example.com", "user\n", "key").build();
+
+        assert!(matches!(result, Err(EvgError::Config { .. })));
+    }
+
+    #[test]
+    fn test_build_should_reject_zero_max_in_flight() {
+        let result = EvgClientBuilder::new("https://evergreen.example.com", "user", "key")
+            .max_in_flight(0)
+            .build();
+        let client = EvgClientBuilder::new("https://evergreen.example.com", "user", "key")
+            .build()
+            .unwrap()
+            .with_max_in_flight(0);
+
+        assert!(matches!(result, Err(EvgError::Config { .. })));
+        assert!(matches!(client, Err(EvgError::Config { .. })));
+    }
+}
@@ -0,0 +1,790 @@
+use crate::models::commit_queue::EvgCommitQueue;
+use crate::models::distro::{EvgDistro, EvgTaskQueueItem};
+use crate::models::host::{EvgHost, EvgHostEvent, EvgHostFilter};
+use crate::models::ids::{BuildId, DistroId, HostId, PatchId, ProjectId, TaskId, VersionId};
+use crate::models::patch::EvgPatchConfiguration;
+use crate::models::project::{EvgProject, EvgProjectAlias, EvgProjectVariables};
+use crate::models::stats::{EvgTaskStats, EvgTaskStatsRequest, EvgTestStats, EvgTestStatsRequest};
+use crate::models::version::EvgVersion;
+use crate::models::{build::EvgBuild, patch::EvgPatch};
+use crate::models::{task::EvgTask, test::EvgTest};
+use crate::{ActionReport, EvgApiClient, EvgError, EvgStream};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Check if an object with a finished status and the given finish time will no longer change.
+fn is_terminal(finished: bool, finish_time: Option<DateTime<Utc>>) -> bool {
+    finished && finish_time.is_some()
+}
+
+struct CacheEntry {
+    value: String,
+    expires_at: Option<Instant>,
+    last_used: u64,
+}
+
+/// In-memory cache of serialized objects, evicting the least recently used entry when full.
+#[derive(Default)]
+struct LruCache {
+    capacity: usize,
+    entries: HashMap<String, CacheEntry>,
+    usage: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl LruCache {
+    fn get(&mut self, key: &str) -> Option<String> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.expires_at.is_some_and(|t| t <= Instant::now()),
+            None => return None,
+        };
+        if expired {
+            self.remove(key);
+            return None;
+        }
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.usage.remove(&entry.last_used);
+        entry.last_used = self.clock;
+        self.usage.insert(self.clock, key.to_string());
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: &str, value: String, expires_at: Option<Instant>) {
+        self.remove(key);
+        while self.entries.len() >= self.capacity.max(1) {
+            let oldest = match self.usage.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(oldest_key) = self.usage.remove(&oldest) {
+                self.entries.remove(&oldest_key);
+            }
+        }
+
+        self.clock += 1;
+        self.usage.insert(self.clock, key.to_string());
+        self.entries.insert(
+            key.to_string(),
+            CacheEntry {
+                value,
+                expires_at,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.usage.remove(&entry.last_used);
+        }
+    }
+}
+
+/// An `EvgApiClient` decorator caching tasks, builds, versions and test lists.
+///
+/// Objects in a terminal state (with a finished status and a finish time) never change, so
+/// they are kept until evicted and, if a disk store is configured, written to disk where they
+/// survive restarts. Test lists are treated as terminal only once their task has been fetched
+/// through this client with `get_task` and found to be terminal; the task is not looked up
+/// just to decide, since that would cost an extra request. Other objects are only cached in
+/// memory for the configured non-terminal TTL, and are not cached at all by default. Disk
+/// failures are treated as cache misses. Task mutations made through this client replace the
+/// cached copy of the task, since a restarted task is no longer terminal, and patch actions
+/// forget the version of the patch along with its builds and tasks.
+pub struct CachingEvgClient<C: EvgApiClient> {
+    inner: C,
+    memory: Mutex<LruCache>,
+    disk_dir: Option<PathBuf>,
+    non_terminal_ttl: Option<Duration>,
+}
+
+impl<C: EvgApiClient> CachingEvgClient<C> {
+    /// Wrap the given client with an in-memory cache.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            memory: Mutex::new(LruCache {
+                capacity: DEFAULT_CAPACITY,
+                ..Default::default()
+            }),
+            disk_dir: None,
+            non_terminal_ttl: None,
+        }
+    }
+
+    /// Maximum number of objects to keep in memory.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        self.memory().capacity = capacity;
+        self
+    }
+
+    /// Also store terminal objects as files under the given directory.
+    pub fn with_disk_store(mut self, dir: &Path) -> Self {
+        self.disk_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Cache objects that are not in a terminal state for the given duration.
+    pub fn with_non_terminal_ttl(mut self, ttl: Duration) -> Self {
+        self.non_terminal_ttl = Some(ttl);
+        self
+    }
+
+    /// The wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Look up the object with the given key, fetching and caching it on a miss.
+    async fn cached<T, Fut>(
+        &self,
+        key: String,
+        fetch: Fut,
+        is_terminal: impl Fn(&T) -> bool,
+    ) -> Result<T, EvgError>
+    where
+        T: Serialize + DeserializeOwned,
+        Fut: Future<Output = Result<T, EvgError>>,
+    {
+        if let Some(value) = self.lookup(&key) {
+            return Ok(value);
+        }
+
+        let value = fetch.await?;
+        self.store(&key, &value, is_terminal(&value));
+        Ok(value)
+    }
+
+    fn lookup<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let cached = self.memory().get(key);
+        if let Some(value) = cached.and_then(|v| serde_json::from_str(&v).ok()) {
+            return Some(value);
+        }
+
+        let contents = fs::read_to_string(self.disk_path(key)?).ok()?;
+        let value = serde_json::from_str(&contents).ok()?;
+        self.memory().insert(key, contents, None);
+        Some(value)
+    }
+
+    fn store<T: Serialize>(&self, key: &str, value: &T, terminal: bool) {
+        let expires_at = match (terminal, self.non_terminal_ttl) {
+            (true, _) => None,
+            (false, Some(ttl)) => Some(Instant::now() + ttl),
+            (false, None) => return,
+        };
+        let serialized = match serde_json::to_string(value) {
+            Ok(serialized) => serialized,
+            Err(_) => return,
+        };
+
+        if terminal {
+            if let Some(path) = self.disk_path(key) {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).ok();
+                }
+                fs::write(&path, &serialized).ok();
+            }
+        }
+        self.memory().insert(key, serialized, expires_at);
+    }
+
+    /// Replace any cached copy of a task with the state returned by a mutation, and drop its
+    /// cached tests.
+    fn refresh_task(&self, task: &EvgTask) {
+        let key = task_key(&task.task_id);
+        self.remove(&key);
+        self.remove(&tests_key(&task.task_id));
+        self.store(
+            &key,
+            task,
+            is_terminal(task.status.is_finished(), task.finish_time),
+        );
+    }
+
+    /// Drop the cached objects changed by an action on the object with the given key.
+    fn forget_action(&self, key: &str, report: &ActionReport) {
+        if report.dry_run {
+            return;
+        }
+        self.remove(key);
+        for task_id in &report.affected_tasks {
+            self.remove(&task_key(task_id));
+            self.remove(&tests_key(task_id));
+        }
+    }
+
+    /// Forget the given version along with its builds and their tasks, after an action that
+    /// does not report the tasks it affected.
+    ///
+    /// The version and builds are read from the cache when possible, and fetched from the inner
+    /// client otherwise to find what else to forget.
+    async fn forget_version(&self, version_id: &VersionId) {
+        let key = format!("version/{}", version_id);
+        let version = match self.lookup::<EvgVersion>(&key) {
+            Some(version) => Some(version),
+            None => self.inner.get_version(version_id).await.ok(),
+        };
+        self.remove(&key);
+
+        let builds = version
+            .and_then(|v| v.build_variants_status)
+            .unwrap_or_default();
+        for build_id in builds.into_iter().map(|b| b.build_id) {
+            let key = format!("build/{}", build_id);
+            let build = match self.lookup::<EvgBuild>(&key) {
+                Some(build) => Some(build),
+                None => self.inner.get_build(&build_id).await.ok().flatten(),
+            };
+            self.remove(&key);
+            for task_id in build.map(|b| b.tasks).unwrap_or_default() {
+                let task_id = TaskId::from(task_id);
+                self.remove(&task_key(&task_id));
+                self.remove(&tests_key(&task_id));
+            }
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        self.memory().remove(key);
+        if let Some(path) = self.disk_path(key) {
+            fs::remove_file(path).ok();
+        }
+    }
+
+    fn is_task_terminal(&self, task_id: &TaskId) -> bool {
+        self.lookup::<EvgTask>(&task_key(task_id))
+            .is_some_and(|task| is_terminal(task.status.is_finished(), task.finish_time))
+    }
+
+    /// Path of the file storing the object with the given key. The id part of the key is
+    /// percent-encoded so that distinct keys never share a file.
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        let (kind, id) = key.split_once('/')?;
+        let file_name: String = id
+            .bytes()
+            .map(|b| match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' => {
+                    char::from(b).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect();
+        Some(
+            self.disk_dir
+                .as_ref()?
+                .join(kind)
+                .join(format!("{}.json", file_name)),
+        )
+    }
+
+    fn memory(&self) -> std::sync::MutexGuard<'_, LruCache> {
+        self.memory.lock().expect("cache lock poisoned")
+    }
+}
+
+fn task_key(task_id: &TaskId) -> String {
+    format!("task/{}", task_id)
+}
+
+fn execution_key(task_id: &TaskId, execution: u32) -> String {
+    format!("execution/{}/{}", task_id, execution)
+}
+
+fn tests_key(task_id: &TaskId) -> String {
+    format!("tests/{}", task_id)
+}
+
+#[async_trait]
+impl<C: EvgApiClient> EvgApiClient for CachingEvgClient<C> {
+    async fn get_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError> {
+        self.cached(task_key(task_id), self.inner.get_task(task_id), |t| {
+            is_terminal(t.status.is_finished(), t.finish_time)
+        })
+        .await
+    }
+
+    async fn get_task_execution(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<EvgTask, EvgError> {
+        let key = execution_key(task_id, execution);
+        self.cached(
+            key,
+            self.inner.get_task_execution(task_id, execution),
+            |t| is_terminal(t.status.is_finished(), t.finish_time),
+        )
+        .await
+    }
+
+    fn stream_task_executions(&self, task_id: &TaskId) -> EvgStream<EvgTask> {
+        self.inner.stream_task_executions(task_id)
+    }
+
+    async fn restart_task(&self, task_id: &TaskId, failed_only: bool) -> Result<EvgTask, EvgError> {
+        let task = self.inner.restart_task(task_id, failed_only).await?;
+        self.refresh_task(&task);
+        Ok(task)
+    }
+
+    async fn abort_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError> {
+        let task = self.inner.abort_task(task_id).await?;
+        self.refresh_task(&task);
+        Ok(task)
+    }
+
+    async fn set_task_activated(
+        &self,
+        task_id: &TaskId,
+        activated: bool,
+    ) -> Result<EvgTask, EvgError> {
+        let task = self.inner.set_task_activated(task_id, activated).await?;
+        self.refresh_task(&task);
+        Ok(task)
+    }
+
+    async fn set_task_priority(
+        &self,
+        task_id: &TaskId,
+        priority: i64,
+    ) -> Result<EvgTask, EvgError> {
+        let task = self.inner.set_task_priority(task_id, priority).await?;
+        self.refresh_task(&task);
+        Ok(task)
+    }
+
+    async fn get_version(&self, version_id: &VersionId) -> Result<EvgVersion, EvgError> {
+        let key = format!("version/{}", version_id);
+        self.cached(key, self.inner.get_version(version_id), |v| {
+            is_terminal(v.status.is_finished(), v.finish_time)
+        })
+        .await
+    }
+
+    async fn get_build(&self, build_id: &BuildId) -> Result<Option<EvgBuild>, EvgError> {
+        let key = format!("build/{}", build_id);
+        let fetch = async {
+            self.inner
+                .get_build(build_id)
+                .await?
+                .ok_or_else(|| EvgError::NotFound { url: key.clone() })
+        };
+        match self
+            .cached(key.clone(), fetch, |b: &EvgBuild| {
+                is_terminal(b.status.is_finished(), b.finish_time)
+            })
+            .await
+        {
+            Ok(build) => Ok(Some(build)),
+            Err(EvgError::NotFound { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn activate_build(
+        &self,
+        build_id: &BuildId,
+        activated: bool,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let report = self
+            .inner
+            .activate_build(build_id, activated, dry_run)
+            .await?;
+        self.forget_action(&format!("build/{}", build_id), &report);
+        Ok(report)
+    }
+
+    async fn restart_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let report = self.inner.restart_build(build_id, dry_run).await?;
+        self.forget_action(&format!("build/{}", build_id), &report);
+        Ok(report)
+    }
+
+    async fn abort_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let report = self.inner.abort_build(build_id, dry_run).await?;
+        self.forget_action(&format!("build/{}", build_id), &report);
+        Ok(report)
+    }
+
+    async fn set_build_priority(
+        &self,
+        build_id: &BuildId,
+        priority: i64,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let report = self
+            .inner
+            .set_build_priority(build_id, priority, dry_run)
+            .await?;
+        self.forget_action(&format!("build/{}", build_id), &report);
+        Ok(report)
+    }
+
+    async fn restart_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let report = self.inner.restart_version(version_id, dry_run).await?;
+        self.forget_action(&format!("version/{}", version_id), &report);
+        Ok(report)
+    }
+
+    async fn abort_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let report = self.inner.abort_version(version_id, dry_run).await?;
+        self.forget_action(&format!("version/{}", version_id), &report);
+        Ok(report)
+    }
+
+    async fn get_tests(&self, task_id: &TaskId) -> Result<Vec<EvgTest>, EvgError> {
+        let key = tests_key(task_id);
+        let terminal = self.is_task_terminal(task_id);
+        self.cached(key, self.inner.get_tests(task_id), |_| terminal)
+            .await
+    }
+
+    async fn get_execution_tests(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<Vec<EvgTest>, EvgError> {
+        self.inner.get_execution_tests(task_id, execution).await
+    }
+
+    async fn get_test_stats(
+        &self,
+        project_id: &ProjectId,
+        query: &EvgTestStatsRequest,
+    ) -> Result<Vec<EvgTestStats>, EvgError> {
+        self.inner.get_test_stats(project_id, query).await
+    }
+
+    async fn get_task_stats(
+        &self,
+        project_id: &ProjectId,
+        query: &EvgTaskStatsRequest,
+    ) -> Result<Vec<EvgTaskStats>, EvgError> {
+        self.inner.get_task_stats(project_id, query).await
+    }
+
+    async fn get_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        self.inner.get_patch(patch_id).await
+    }
+
+    async fn abort_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        let patch = self.inner.abort_patch(patch_id).await?;
+        self.forget_version(&patch.version).await;
+        Ok(patch)
+    }
+
+    async fn restart_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        let patch = self.inner.restart_patch(patch_id).await?;
+        self.forget_version(&patch.version).await;
+        Ok(patch)
+    }
+
+    async fn set_patch_priority(
+        &self,
+        patch_id: &PatchId,
+        priority: i64,
+    ) -> Result<EvgPatch, EvgError> {
+        self.inner.set_patch_priority(patch_id, priority).await
+    }
+
+    async fn activate_patch(
+        &self,
+        patch_id: &PatchId,
+        activated: bool,
+    ) -> Result<EvgPatch, EvgError> {
+        self.inner.activate_patch(patch_id, activated).await
+    }
+
+    async fn configure_patch(
+        &self,
+        patch_id: &PatchId,
+        configuration: &EvgPatchConfiguration,
+    ) -> Result<EvgPatch, EvgError> {
+        self.inner.configure_patch(patch_id, configuration).await
+    }
+
+    async fn list_projects(&self) -> Result<Vec<EvgProject>, EvgError> {
+        self.inner.list_projects().await
+    }
+
+    async fn get_project(&self, project_id: &ProjectId) -> Result<EvgProject, EvgError> {
+        self.inner.get_project(project_id).await
+    }
+
+    async fn get_project_variables(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<EvgProjectVariables, EvgError> {
+        self.inner.get_project_variables(project_id).await
+    }
+
+    async fn get_project_aliases(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<Vec<EvgProjectAlias>, EvgError> {
+        self.inner.get_project_aliases(project_id).await
+    }
+
+    async fn get_host(&self, host_id: &HostId) -> Result<EvgHost, EvgError> {
+        self.inner.get_host(host_id).await
+    }
+
+    async fn get_host_events(&self, host_id: &HostId) -> Result<Vec<EvgHostEvent>, EvgError> {
+        self.inner.get_host_events(host_id).await
+    }
+
+    async fn get_commit_queue(&self, project_id: &ProjectId) -> Result<EvgCommitQueue, EvgError> {
+        self.inner.get_commit_queue(project_id).await
+    }
+
+    async fn enqueue_patch(&self, patch_id: &PatchId, force: bool) -> Result<usize, EvgError> {
+        self.inner.enqueue_patch(patch_id, force).await
+    }
+
+    async fn dequeue_patch(
+        &self,
+        project_id: &ProjectId,
+        patch_id: &PatchId,
+    ) -> Result<(), EvgError> {
+        self.inner.dequeue_patch(project_id, patch_id).await
+    }
+
+    async fn list_distros(&self) -> Result<Vec<EvgDistro>, EvgError> {
+        self.inner.list_distros().await
+    }
+
+    async fn get_distro(&self, distro_id: &DistroId) -> Result<EvgDistro, EvgError> {
+        self.inner.get_distro(distro_id).await
+    }
+
+    fn stream_hosts(&self, filter: &EvgHostFilter) -> EvgStream<EvgHost> {
+        self.inner.stream_hosts(filter)
+    }
+
+    fn stream_distro_queue(&self, distro_id: &DistroId) -> EvgStream<EvgTaskQueueItem> {
+        self.inner.stream_distro_queue(distro_id)
+    }
+
+    fn stream_versions(&self, project_id: &ProjectId) -> EvgStream<EvgVersion> {
+        self.inner.stream_versions(project_id)
+    }
+
+    fn stream_user_patches(&self, user_id: &str, limit: Option<usize>) -> EvgStream<EvgPatch> {
+        self.inner.stream_user_patches(user_id, limit)
+    }
+
+    fn stream_project_patches(
+        &self,
+        project_id: &ProjectId,
+        limit: Option<usize>,
+    ) -> EvgStream<EvgPatch> {
+        self.inner.stream_project_patches(project_id, limit)
+    }
+
+    fn stream_build_tasks(&self, build_id: &BuildId, status: Option<&str>) -> EvgStream<EvgTask> {
+        self.inner.stream_build_tasks(build_id, status)
+    }
+
+    fn stream_log(&self, task: &EvgTask, log_name: &str) -> EvgStream<String> {
+        self.inner.stream_log(task, log_name)
+    }
+
+    fn stream_execution_log(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+        log_name: &str,
+    ) -> EvgStream<String> {
+        self.inner
+            .stream_execution_log(task_id, execution, log_name)
+    }
+
+    fn stream_test_log(&self, test: &EvgTest) -> EvgStream<String> {
+        self.inner.stream_test_log(test)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::{self, build_json, patch_json, version_json};
+    use crate::testing::FakeEvgClient;
+
+    #[tokio::test]
+    async fn test_get_task_should_only_cache_terminal_tasks() {
+        let client = CachingEvgClient::new(FakeEvgClient::new());
+        client
+            .inner()
+            .insert_task(fixtures::finished_task("finished", "build", "success"));
+        client
+            .inner()
+            .insert_task(fixtures::task("running", "build", "started"));
+
+        client.get_task(&"finished".into()).await.unwrap();
+        client.get_task(&"running".into()).await.unwrap();
+        client
+            .inner()
+            .insert_task(fixtures::finished_task("finished", "build", "failed"));
+        client
+            .inner()
+            .insert_task(fixtures::finished_task("running", "build", "failed"));
+
+        assert_eq!(
+            client.get_task(&"finished".into()).await.unwrap().status,
+            "success"
+        );
+        assert_eq!(
+            client.get_task(&"running".into()).await.unwrap().status,
+            "failed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restart_task_should_replace_cached_task() {
+        let client = CachingEvgClient::new(FakeEvgClient::new());
+        client
+            .inner()
+            .insert_task(fixtures::finished_task("finished", "build", "failed"));
+        client.get_task(&"finished".into()).await.unwrap();
+
+        client
+            .restart_task(&"finished".into(), false)
+            .await
+            .unwrap();
+
+        let task = client.get_task(&"finished".into()).await.unwrap();
+        assert_eq!(task.status, "undispatched");
+        assert_eq!(task.execution, 1);
+    }
+
+    #[tokio::test]
+    async fn test_restart_patch_should_forget_version_builds_and_tasks() {
+        let client = CachingEvgClient::new(FakeEvgClient::new());
+        let mut version = version_json("version", 1);
+        version["finish_time"] = serde_json::json!("2021-01-01T01:00:00Z");
+        version["build_variants_status"] =
+            serde_json::json!([{ "build_variant": "variant", "build_id": "build" }]);
+        let mut build = build_json("build", "version", "failed");
+        build["finish_time"] = serde_json::json!("2021-01-01T01:00:00Z");
+        build["tasks"] = serde_json::json!(["task"]);
+        let mut patch = patch_json("patch", "failed");
+        patch["version"] = serde_json::json!("version");
+        client.inner().insert_version(fixtures::parse(version));
+        client.inner().insert_build(fixtures::parse(build));
+        client.inner().insert_patch(fixtures::parse(patch));
+        client
+            .inner()
+            .insert_task(fixtures::finished_task("task", "build", "failed"));
+        client.get_version(&"version".into()).await.unwrap();
+        client.get_build(&"build".into()).await.unwrap();
+        client.get_task(&"task".into()).await.unwrap();
+
+        client
+            .inner()
+            .insert_task(fixtures::task("task", "build", "undispatched"));
+        client.restart_patch(&"patch".into()).await.unwrap();
+
+        assert_eq!(
+            client.get_task(&"task".into()).await.unwrap().status,
+            "undispatched"
+        );
+        assert!(client.memory().get("version/version").is_none());
+        assert!(client.memory().get("build/build").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_task_should_read_terminal_tasks_from_disk() {
+        let dir = std::env::temp_dir().join(format!("evg-cache-{}", std::process::id()));
+        let writer = CachingEvgClient::new(FakeEvgClient::new()).with_disk_store(&dir);
+        writer
+            .inner()
+            .insert_task(fixtures::finished_task("finished", "build", "success"));
+        writer.get_task(&"finished".into()).await.unwrap();
+
+        let reader = CachingEvgClient::new(FakeEvgClient::new()).with_disk_store(&dir);
+        let cached = reader.get_task(&"finished".into()).await;
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(cached.unwrap().task_id, "finished");
+    }
+
+    #[tokio::test]
+    async fn test_get_task_should_fetch_non_terminal_tasks_again_after_ttl() {
+        let client = CachingEvgClient::new(FakeEvgClient::new())
+            .with_non_terminal_ttl(Duration::from_millis(20));
+        client
+            .inner()
+            .insert_task(fixtures::task("running", "build", "started"));
+
+        client.get_task(&"running".into()).await.unwrap();
+        client
+            .inner()
+            .insert_task(fixtures::task("running", "build", "failed"));
+        let cached = client.get_task(&"running".into()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        let refetched = client.get_task(&"running".into()).await.unwrap();
+
+        assert_eq!(cached.status, "started");
+        assert_eq!(refetched.status, "failed");
+    }
+
+    #[test]
+    fn test_disk_path_should_not_collide() {
+        let client =
+            CachingEvgClient::new(FakeEvgClient::new()).with_disk_store(Path::new("cache"));
+
+        let slash = client.disk_path("execution/task_0/1").unwrap();
+        let underscore = client.disk_path("execution/task_0_1").unwrap();
+        let percent = client.disk_path("execution/task_0%2F1").unwrap();
+
+        assert_eq!(slash, Path::new("cache/execution/task_0%2F1.json"));
+        assert_ne!(slash, underscore);
+        assert_ne!(slash, percent);
+    }
+
+    #[test]
+    fn test_lru_cache_should_evict_least_recently_used() {
+        let mut cache = LruCache {
+            capacity: 2,
+            ..Default::default()
+        };
+        cache.insert("a", String::from("1"), None);
+        cache.insert("b", String::from("2"), None);
+        cache.get("a");
+        cache.insert("c", String::from("3"), None);
+
+        assert_eq!(cache.get("a"), Some(String::from("1")));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(String::from("3")));
+    }
+}
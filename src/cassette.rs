@@ -0,0 +1,446 @@
+use crate::EvgError;
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Value recorded in place of the `Api-Key` header and of private project variables.
+const REDACTED: &str = "REDACTED";
+/// Headers describing the encoding of the original body, which no longer apply once recorded.
+const SKIPPED_RESPONSE_HEADERS: &[&str] = &["content-length", "transfer-encoding"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<RecordedBody>,
+}
+
+/// A response body, kept as text when it is valid UTF-8 so cassettes stay readable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedBody {
+    Text(String),
+    Base64(String),
+}
+
+impl RecordedBody {
+    fn new(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text),
+            Err(err) => RecordedBody::Base64(base64::encode(err.into_bytes())),
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, EvgError> {
+        match self {
+            RecordedBody::Text(text) => Ok(text.clone().into_bytes()),
+            RecordedBody::Base64(encoded) => base64::decode(encoded)
+                .map_err(|e| EvgError::cassette_with_source("could not decode a recorded body", e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug)]
+enum CassetteState {
+    Record {
+        file: File,
+    },
+    Replay {
+        interactions: Vec<Interaction>,
+        served: HashMap<String, usize>,
+    },
+}
+
+/// A file of recorded request/response pairs, one JSON document per line.
+///
+/// In record mode every interaction is appended to the file as it happens, with the `Api-Key`
+/// header and the values of private project variables redacted. Bodies that are not valid
+/// UTF-8 are stored as base64 so they replay byte for byte. In replay mode
+/// responses are served from the file without touching the network; requests are matched on
+/// their method, path, query and, except for GET and HEAD requests, body so a cassette can be
+/// replayed against any server host.
+/// Repeated requests are answered with the recorded responses in order, reusing the last one
+/// once they run out.
+#[derive(Debug)]
+pub(crate) struct Cassette {
+    path: PathBuf,
+    state: Mutex<CassetteState>,
+}
+
+impl Cassette {
+    /// Start recording a new cassette at the given path.
+    pub(crate) fn record(path: &Path) -> Result<Self, EvgError> {
+        let file = File::create(path).map_err(|e| {
+            EvgError::cassette_with_source(format!("could not create '{}'", path.display()), e)
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            state: Mutex::new(CassetteState::Record { file }),
+        })
+    }
+
+    /// Load a previously recorded cassette for replay.
+    pub(crate) fn replay(path: &Path) -> Result<Self, EvgError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            EvgError::cassette_with_source(format!("could not read '{}'", path.display()), e)
+        })?;
+        let interactions = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Interaction>, _>>()
+            .map_err(|e| {
+                EvgError::cassette_with_source(format!("could not parse '{}'", path.display()), e)
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            state: Mutex::new(CassetteState::Replay {
+                interactions,
+                served: HashMap::new(),
+            }),
+        })
+    }
+
+    pub(crate) fn is_replay(&self) -> bool {
+        matches!(*self.state(), CassetteState::Replay { .. })
+    }
+
+    /// Answer the given request from the recorded interactions.
+    pub(crate) fn play(&self, request: &Request) -> Result<Response, EvgError> {
+        let method = request.method().to_string();
+        let key = match_key(&method, request.url(), request_body(request).as_ref());
+        let mut state = self.state();
+        let (interactions, served) = match &mut *state {
+            CassetteState::Replay {
+                interactions,
+                served,
+            } => (interactions, served),
+            CassetteState::Record { .. } => unreachable!("play is only called in replay mode"),
+        };
+
+        let candidates: Vec<&Interaction> = interactions
+            .iter()
+            .filter(|i| {
+                Url::parse(&i.request.url)
+                    .map(|url| match_key(&i.request.method, &url, i.request.body.as_ref()) == key)
+                    .unwrap_or(false)
+            })
+            .collect();
+        let count = served.entry(key).or_insert(0);
+        let interaction = candidates
+            .get(*count)
+            .or_else(|| candidates.last())
+            .ok_or_else(|| {
+                EvgError::cassette(format!(
+                    "no recorded response for {} {}",
+                    method,
+                    request.url()
+                ))
+            })?;
+        *count += 1;
+
+        to_response(&interaction.response, request.url())
+    }
+
+    /// Record the given request and response, returning an equivalent response to the caller.
+    pub(crate) async fn store(
+        &self,
+        request: &Request,
+        response: Response,
+    ) -> Result<Response, EvgError> {
+        let url = response.url().clone();
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !SKIPPED_RESPONSE_HEADERS.contains(&name.as_str()))
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        let body = response.bytes().await?.to_vec();
+
+        let recorded = RecordedResponse {
+            status,
+            headers,
+            body: RecordedBody::new(redact_variables(&body).unwrap_or_else(|| body.clone())),
+        };
+        let interaction = Interaction {
+            request: RecordedRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                headers: request
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = if name.as_str().eq_ignore_ascii_case("api-key") {
+                            REDACTED.to_string()
+                        } else {
+                            String::from_utf8_lossy(value.as_bytes()).into_owned()
+                        };
+                        (name.to_string(), value)
+                    })
+                    .collect(),
+                body: request_body(request),
+            },
+            response: recorded,
+        };
+        self.append(&interaction)?;
+
+        Ok(build_response(&interaction.response, body, &url))
+    }
+
+    /// Append the given interaction to the cassette file.
+    fn append(&self, interaction: &Interaction) -> Result<(), EvgError> {
+        let mut line = serde_json::to_string(interaction).expect("cassettes can be serialized");
+        line.push('\n');
+        let mut state = self.state();
+        let file = match &mut *state {
+            CassetteState::Record { file } => file,
+            CassetteState::Replay { .. } => unreachable!("store is only called in record mode"),
+        };
+        file.write_all(line.as_bytes()).map_err(|e| {
+            EvgError::cassette_with_source(format!("could not write '{}'", self.path.display()), e)
+        })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CassetteState> {
+        self.state.lock().expect("cassette lock poisoned")
+    }
+}
+
+/// Key identifying equivalent requests regardless of the server they were sent to.
+fn match_key(method: &str, url: &Url, body: Option<&RecordedBody>) -> String {
+    let mut key = match url.query() {
+        Some(query) => format!("{} {}?{}", method, url.path(), query),
+        None => format!("{} {}", method, url.path()),
+    };
+    let replayable = method == "GET" || method == "HEAD";
+    if let Some(body) = body.filter(|_| !replayable) {
+        key.push(' ');
+        key.push_str(&serde_json::to_string(body).expect("bodies can be serialized"));
+    }
+    key
+}
+
+/// The body of the given request as recorded. JSON bodies have their private variables
+/// redacted and are written with sorted keys, so equal bodies are always recorded the same way.
+fn request_body(request: &Request) -> Option<RecordedBody> {
+    let bytes = request.body()?.as_bytes()?;
+    let body = match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut json) => {
+            redact_value(&mut json);
+            serde_json::to_vec(&json).expect("JSON values can be serialized")
+        }
+        Err(_) => bytes.to_vec(),
+    };
+    Some(RecordedBody::new(body))
+}
+
+fn to_response(recorded: &RecordedResponse, url: &Url) -> Result<Response, EvgError> {
+    Ok(build_response(recorded, recorded.body.to_bytes()?, url))
+}
+
+/// Build a response with the status and headers of the recorded one and the given body.
+fn build_response(recorded: &RecordedResponse, body: Vec<u8>, url: &Url) -> Response {
+    let mut builder = http::Response::builder()
+        .status(recorded.status)
+        .url(url.clone());
+    for (name, value) in &recorded.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    Response::from(builder.body(body).expect("recorded responses are valid"))
+}
+
+/// Copy of the given JSON body with the values of private project variables replaced with
+/// `REDACTED`, or `None` if it has none.
+fn redact_variables(body: &[u8]) -> Option<Vec<u8>> {
+    let mut json: Value = serde_json::from_slice(body).ok()?;
+    if !redact_value(&mut json) {
+        return None;
+    }
+    Some(serde_json::to_vec(&json).expect("JSON values can be serialized"))
+}
+
+/// Redact the private variables of every set of project variables in the given value,
+/// returning whether anything was redacted.
+fn redact_value(value: &mut Value) -> bool {
+    match value {
+        Value::Object(object) => {
+            let private: Vec<String> = object
+                .get("private_vars")
+                .and_then(Value::as_object)
+                .map(|private_vars| {
+                    private_vars
+                        .iter()
+                        .filter(|(_, is_private)| is_private.as_bool() == Some(true))
+                        .map(|(name, _)| name.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let mut redacted = false;
+            if let Some(Value::Object(vars)) = object.get_mut("vars") {
+                for name in &private {
+                    if let Some(var) = vars.get_mut(name) {
+                        *var = Value::from(REDACTED);
+                        redacted = true;
+                    }
+                }
+            }
+            for value in object.values_mut() {
+                redacted |= redact_value(value);
+            }
+            redacted
+        }
+        Value::Array(items) => {
+            let mut redacted = false;
+            for item in items {
+                redacted |= redact_value(item);
+            }
+            redacted
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::{patch_json, task_json};
+    use crate::testing::StubServer;
+    use crate::{EvgApiClient, EvgClient};
+
+    #[tokio::test]
+    async fn test_replay_should_serve_recorded_responses_without_network() {
+        let path = std::env::temp_dir().join(format!("evg-cassette-{}.json", std::process::id()));
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0",
+            &task_json("task_0", "build", "failed"),
+        );
+        let recorder = server.client().record_to(&path).unwrap();
+        recorder.get_task(&"task_0".into()).await.unwrap();
+        drop(server);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let replayer = EvgClient::builder("http://unreachable.invalid", "user", "key")
+            .build()
+            .unwrap()
+            .replay_from(&path)
+            .unwrap();
+        let task = replayer.get_task(&"task_0".into()).await.unwrap();
+        let missing = replayer.get_task(&"task_1".into()).await;
+        fs::remove_file(&path).ok();
+
+        assert!(contents.contains(REDACTED));
+        assert!(!contents.contains("stub_key"));
+        assert_eq!(task.task_id, "task_0");
+        assert!(matches!(missing, Err(EvgError::Cassette { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_record_should_append_interactions_and_redact_private_variables() {
+        let path =
+            std::env::temp_dir().join(format!("evg-cassette-vars-{}.json", std::process::id()));
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0",
+            &task_json("task_0", "build", "failed"),
+        );
+        server.add_json(
+            "/rest/v2/projects/mongo/variables",
+            &serde_json::json!({
+                "vars": { "token": "secret", "region": "us-east-1" },
+                "private_vars": { "token": true }
+            }),
+        );
+        let recorder = server.client().record_to(&path).unwrap();
+        recorder.get_task(&"task_0".into()).await.unwrap();
+        let recorded = recorder
+            .get_project_variables(&"mongo".into())
+            .await
+            .unwrap();
+        drop(server);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let replayer = EvgClient::builder("http://unreachable.invalid", "user", "key")
+            .build()
+            .unwrap()
+            .replay_from(&path)
+            .unwrap();
+        let replayed = replayer
+            .get_project_variables(&"mongo".into())
+            .await
+            .unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(contents.lines().count(), 2);
+        assert!(!contents.contains("secret"));
+        assert_eq!(recorded.vars["region"], "us-east-1");
+        assert_eq!(replayed.vars["region"], "us-east-1");
+        assert_eq!(replayed.vars["token"], recorded.vars["token"]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_should_match_request_bodies() {
+        let path =
+            std::env::temp_dir().join(format!("evg-cassette-body-{}.json", std::process::id()));
+        let server = StubServer::start().await;
+        let recorder = server.client().record_to(&path).unwrap();
+        for (priority, status) in &[(10, "created"), (20, "started")] {
+            server.add_json("/rest/v2/patches/patch_0", &patch_json("patch_0", status));
+            recorder
+                .set_patch_priority(&"patch_0".into(), *priority)
+                .await
+                .unwrap();
+        }
+        drop(server);
+
+        let replayer = EvgClient::builder("http://unreachable.invalid", "user", "key")
+            .build()
+            .unwrap()
+            .replay_from(&path)
+            .unwrap();
+        let second = replayer.set_patch_priority(&"patch_0".into(), 20).await;
+        let first = replayer.set_patch_priority(&"patch_0".into(), 10).await;
+        let unrecorded = replayer.set_patch_priority(&"patch_0".into(), 30).await;
+        fs::remove_file(&path).ok();
+
+        assert_eq!(second.unwrap().status, "started");
+        assert_eq!(first.unwrap().status, "created");
+        assert!(matches!(unrecorded, Err(EvgError::Cassette { .. })));
+    }
+
+    #[test]
+    fn test_recorded_body_should_keep_binary_bodies() {
+        let bytes = vec![0x1f, 0x8b, 0xff, 0x00];
+
+        let body = RecordedBody::new(bytes.clone());
+
+        assert!(matches!(body, RecordedBody::Base64(_)));
+        assert_eq!(body.to_bytes().unwrap(), bytes);
+    }
+}
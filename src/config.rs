@@ -0,0 +1,242 @@
+use crate::EvgError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_CONFIG_FILE: &str = ".evergreen.yml";
+const XDG_CONFIG_FILE: &str = "evergreen.yml";
+const CONFIG_PATH_VAR: &str = "EVERGREEN_CONFIG";
+const USER_VAR: &str = "EVG_API_USER";
+const API_KEY_VAR: &str = "EVG_API_KEY";
+const SERVER_HOST_VAR: &str = "EVG_API_SERVER_HOST";
+
+/// Connection details for a single evergreen deployment.
+#[derive(Debug, Clone)]
+pub(crate) struct EvergreenConfig {
+    pub user: String,
+    pub api_key: String,
+    pub api_server_host: String,
+}
+
+/// Settings of a profile; missing settings fall back to the top level of the file.
+#[derive(Debug, Default, Deserialize, Clone)]
+struct EvergreenProfile {
+    user: Option<String>,
+    api_key: Option<String>,
+    api_server_host: Option<String>,
+}
+
+/// Contents of an evergreen config file.
+///
+/// The flat format with `user`, `api_key` and `api_server_host` at the top level is supported
+/// alongside named `profiles` and an optional `default_profile`.
+#[derive(Debug, Deserialize, Clone)]
+struct EvergreenConfigFile {
+    #[serde(flatten)]
+    defaults: EvergreenProfile,
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: HashMap<String, EvergreenProfile>,
+}
+
+impl EvergreenConfigFile {
+    /// Resolve the named profile, or the default profile if no name is given.
+    fn resolve(&self, profile_name: Option<&str>) -> Result<EvergreenConfig, EvgError> {
+        let profile_name = profile_name.or(self.default_profile.as_deref());
+        let profile = match profile_name {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .ok_or_else(|| EvgError::config(format!("profile '{}' is not defined", name)))?,
+            None => &self.defaults,
+        };
+
+        let setting = |value: &Option<String>, default: &Option<String>, key: &str| {
+            value.clone().or_else(|| default.clone()).ok_or_else(|| {
+                EvgError::config(match profile_name {
+                    Some(name) => format!("profile '{}' is missing '{}'", name, key),
+                    None => format!("missing '{}'", key),
+                })
+            })
+        };
+
+        Ok(EvergreenConfig {
+            user: setting(&profile.user, &self.defaults.user, "user")?,
+            api_key: setting(&profile.api_key, &self.defaults.api_key, "api_key")?,
+            api_server_host: setting(
+                &profile.api_server_host,
+                &self.defaults.api_server_host,
+                "api_server_host",
+            )?,
+        })
+    }
+}
+
+/// Read the given profile (or the default one) from the evergreen config file at `path`.
+pub(crate) fn get_evg_config(
+    path: &Path,
+    profile_name: Option<&str>,
+) -> Result<EvergreenConfig, EvgError> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        EvgError::config_with_source(format!("could not read '{}'", path.display()), e)
+    })?;
+    let config_file: EvergreenConfigFile = serde_yaml::from_str(&contents).map_err(|e| {
+        EvgError::config_with_source(format!("could not parse '{}'", path.display()), e)
+    })?;
+    config_file.resolve(profile_name).map_err(|e| match e {
+        EvgError::Config { message, source } => EvgError::Config {
+            message: format!("{} in '{}'", message, path.display()),
+            source,
+        },
+        e => e,
+    })
+}
+
+/// Look up an environment variable, treating empty values as unset.
+pub(crate) fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Find the evergreen configuration, looking up environment variables with `env`.
+///
+/// An explicit `EVERGREEN_CONFIG` path is used first, then the `EVG_API_*` variables, then
+/// `$XDG_CONFIG_HOME/evergreen.yml` and finally `$HOME/.evergreen.yml`. When a profile name is
+/// given, the `EVG_API_*` variables are skipped since they do not describe named profiles.
+pub(crate) fn discover_config<F>(
+    env: F,
+    profile_name: Option<&str>,
+) -> Result<EvergreenConfig, EvgError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(path) = env(CONFIG_PATH_VAR) {
+        return get_evg_config(Path::new(&path), profile_name);
+    }
+    let mut tried = vec![format!("{} (not set)", CONFIG_PATH_VAR)];
+
+    if profile_name.is_none() {
+        let vars = [USER_VAR, API_KEY_VAR, SERVER_HOST_VAR];
+        let values: Vec<Option<String>> = vars.iter().map(|name| env(name)).collect();
+        if let [Some(user), Some(api_key), Some(api_server_host)] = values.as_slice() {
+            return Ok(EvergreenConfig {
+                user: user.clone(),
+                api_key: api_key.clone(),
+                api_server_host: api_server_host.clone(),
+            });
+        }
+        let missing: Vec<&str> = vars
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+        tried.push(format!("environment ({} not set)", missing.join(", ")));
+    }
+
+    let candidates = [
+        ("XDG_CONFIG_HOME", XDG_CONFIG_FILE),
+        ("HOME", DEFAULT_CONFIG_FILE),
+    ];
+    for (dir_var, file_name) in candidates.iter() {
+        match env(dir_var) {
+            Some(dir) => {
+                let path: PathBuf = Path::new(&dir).join(file_name);
+                if path.exists() {
+                    return get_evg_config(&path, profile_name);
+                }
+                tried.push(format!("{} (not found)", path.display()));
+            }
+            None => tried.push(format!("${}/{} ({} not set)", dir_var, file_name, dir_var)),
+        }
+    }
+
+    Err(EvgError::config(format!(
+        "no evergreen configuration found, tried: {}",
+        tried.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_discover_config_should_use_environment_credentials() {
+        let env = env_from(&[
+            (USER_VAR, "user"),
+            (API_KEY_VAR, "key"),
+            (SERVER_HOST_VAR, "https://evergreen.example.com"),
+        ]);
+
+        let evg_config = discover_config(env, None).unwrap();
+
+        assert_eq!(evg_config.user, "user");
+        assert_eq!(evg_config.api_key, "key");
+        assert_eq!(evg_config.api_server_host, "https://evergreen.example.com");
+    }
+
+    #[test]
+    fn test_discover_config_should_report_sources_tried() {
+        let env = env_from(&[(USER_VAR, "user"), ("HOME", "/does/not/exist")]);
+
+        let err = discover_config(env, None).unwrap_err().to_string();
+
+        assert!(err.contains("EVERGREEN_CONFIG (not set)"));
+        assert!(err.contains("EVG_API_KEY, EVG_API_SERVER_HOST not set"));
+        assert!(err.contains("$XDG_CONFIG_HOME/evergreen.yml (XDG_CONFIG_HOME not set)"));
+        assert!(err.contains("/does/not/exist/.evergreen.yml (not found)"));
+    }
+
+    #[test]
+    fn test_resolve_should_support_flat_format() {
+        let config_file: EvergreenConfigFile = serde_yaml::from_str(
+            "user: user\napi_key: key\napi_server_host: https://evergreen.example.com\n",
+        )
+        .unwrap();
+
+        let evg_config = config_file.resolve(None).unwrap();
+
+        assert_eq!(evg_config.user, "user");
+        assert_eq!(evg_config.api_server_host, "https://evergreen.example.com");
+    }
+
+    #[test]
+    fn test_resolve_should_fall_back_to_top_level_settings_for_profiles() {
+        let config_file: EvergreenConfigFile = serde_yaml::from_str(
+            r#"
+user: user
+api_key: key
+api_server_host: https://evergreen.example.com
+default_profile: staging
+profiles:
+  staging:
+    api_server_host: https://evergreen-staging.example.com
+  other:
+    user: other_user
+    api_key: other_key
+    api_server_host: https://evergreen-other.example.com
+"#,
+        )
+        .unwrap();
+
+        let staging = config_file.resolve(None).unwrap();
+        let other = config_file.resolve(Some("other")).unwrap();
+
+        assert_eq!(staging.user, "user");
+        assert_eq!(
+            staging.api_server_host,
+            "https://evergreen-staging.example.com"
+        );
+        assert_eq!(other.user, "other_user");
+        assert!(config_file.resolve(Some("missing")).is_err());
+    }
+}
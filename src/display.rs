@@ -0,0 +1,109 @@
+use crate::models::ids::TaskId;
+use crate::models::task::EvgTask;
+use crate::models::test::EvgTest;
+use crate::{EvgApiClient, EvgError};
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Maximum number of execution tasks fetched at the same time.
+const EXPANSION_CONCURRENCY: usize = 8;
+
+/// An execution task of a display task, along with its tests.
+#[derive(Debug, Clone)]
+pub struct ExecutionTaskNode {
+    pub task: EvgTask,
+    pub tests: Vec<EvgTest>,
+}
+
+/// A display task expanded into its execution tasks.
+#[derive(Debug, Clone)]
+pub struct DisplayTaskTree {
+    pub display_task: EvgTask,
+    pub execution_tasks: Vec<ExecutionTaskNode>,
+}
+
+impl DisplayTaskTree {
+    /// Tests of all execution tasks.
+    pub fn tests(&self) -> impl Iterator<Item = &EvgTest> {
+        self.execution_tasks
+            .iter()
+            .flat_map(|node| node.tests.iter())
+    }
+
+    /// Failed tests of all execution tasks.
+    pub fn failed_tests(&self) -> impl Iterator<Item = &EvgTest> {
+        self.tests().filter(|test| test.status.is_failure())
+    }
+
+    /// Execution tasks that finished unsuccessfully.
+    pub fn failed_execution_tasks(&self) -> impl Iterator<Item = &EvgTask> {
+        self.execution_tasks
+            .iter()
+            .map(|node| &node.task)
+            .filter(|task| task.status.is_failure())
+    }
+}
+
+/// Fetch the execution tasks of the given display task and their tests.
+///
+/// A task that is not a display task is returned as its own single execution task.
+pub(crate) async fn expand<C>(client: &C, task: &EvgTask) -> Result<DisplayTaskTree, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+{
+    let task_ids: Vec<TaskId> = if task.display_only {
+        task.execution_tasks.clone().unwrap_or_default()
+    } else {
+        vec![task.task_id.clone()]
+    };
+
+    let execution_tasks = stream::iter(task_ids)
+        .map(|task_id| async move {
+            let (task, tests) =
+                futures::try_join!(client.get_task(&task_id), client.get_tests(&task_id))?;
+            Ok::<_, EvgError>(ExecutionTaskNode { task, tests })
+        })
+        .buffered(EXPANSION_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    Ok(DisplayTaskTree {
+        display_task: task.clone(),
+        execution_tasks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use crate::testing::FakeEvgClient;
+
+    #[tokio::test]
+    async fn test_expand_display_task_should_fetch_execution_tasks_and_tests() {
+        let client = FakeEvgClient::new();
+        let mut display_task = fixtures::task("display", "build", "failed");
+        display_task.display_only = true;
+        display_task.execution_tasks = Some(vec!["exec_0".into(), "exec_1".into()]);
+        client.insert_task(fixtures::task("exec_0", "build", "success"));
+        client.insert_task(fixtures::task("exec_1", "build", "failed"));
+        for (task_id, test_file, status) in &[
+            ("exec_0", "test_a", "pass"),
+            ("exec_1", "test_b", "pass"),
+            ("exec_1", "test_c", "fail"),
+        ] {
+            client.insert_test(fixtures::test(task_id, test_file, status));
+        }
+
+        let tree = client.expand_display_task(&display_task).await.unwrap();
+
+        let failed_tasks: Vec<&str> = tree
+            .failed_execution_tasks()
+            .map(|t| t.task_id.as_str())
+            .collect();
+        let failed_tests: Vec<&str> = tree.failed_tests().map(|t| t.test_file.as_str()).collect();
+        assert_eq!(tree.execution_tasks.len(), 2);
+        assert_eq!(tree.tests().count(), 3);
+        assert_eq!(failed_tasks, vec!["exec_1"]);
+        assert_eq!(failed_tests, vec!["test_c"]);
+    }
+}
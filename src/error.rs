@@ -0,0 +1,183 @@
+use crate::models::ids::TaskId;
+use reqwest::StatusCode;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Maximum number of bytes of a response body to keep when reporting a decode failure.
+const SNIPPET_LEN: usize = 256;
+
+/// Errors that can occur while talking to the Evergreen API.
+#[derive(Debug)]
+pub enum EvgError {
+    /// The evergreen configuration could not be located, read or parsed, or a request option
+    /// is invalid.
+    Config {
+        message: String,
+        source: Option<Box<dyn Error + Sync + Send>>,
+    },
+    /// The request could not be sent or the response could not be read.
+    Transport(reqwest::Error),
+    /// The server responded with a non-success status code.
+    HttpStatus {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// The response body could not be deserialized into the expected type.
+    Deserialize {
+        url: String,
+        snippet: String,
+        source: serde_json::Error,
+    },
+    /// The requested object does not exist.
+    NotFound { url: String },
+    /// The task does not have a log with the given name, or its test of that name has no log.
+    MissingLog { task_id: TaskId, log_name: String },
+    /// An object did not reach a terminal status before the wait timed out.
+    WaitTimeout { elapsed: Duration },
+    /// A cassette could not be read or written, or has no response for a replayed request.
+    Cassette {
+        message: String,
+        source: Option<Box<dyn Error + Sync + Send>>,
+    },
+}
+
+impl EvgError {
+    /// Create a configuration error with the given message.
+    pub(crate) fn config(message: impl Into<String>) -> Self {
+        EvgError::Config {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Create a configuration error caused by the given error.
+    pub(crate) fn config_with_source(
+        message: impl Into<String>,
+        source: impl Into<Box<dyn Error + Sync + Send>>,
+    ) -> Self {
+        EvgError::Config {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Create a cassette error with the given message.
+    pub(crate) fn cassette(message: impl Into<String>) -> Self {
+        EvgError::Cassette {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Create a cassette error caused by the given error.
+    pub(crate) fn cassette_with_source(
+        message: impl Into<String>,
+        source: impl Into<Box<dyn Error + Sync + Send>>,
+    ) -> Self {
+        EvgError::Cassette {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Create a deserialization error for the given endpoint and payload.
+    pub(crate) fn deserialize(url: &str, body: &[u8], source: serde_json::Error) -> Self {
+        let end = body.len().min(SNIPPET_LEN);
+        EvgError::Deserialize {
+            url: url.to_string(),
+            snippet: String::from_utf8_lossy(&body[..end]).into_owned(),
+            source,
+        }
+    }
+
+    /// The HTTP status code associated with this error, if any.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            EvgError::HttpStatus { status, .. } => Some(*status),
+            EvgError::NotFound { .. } => Some(StatusCode::NOT_FOUND),
+            EvgError::Transport(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    /// Check if this error is likely to go away when the request is sent again later, such as
+    /// a network failure or a server error.
+    ///
+    /// Only transport errors raised while connecting or sending the request, or timeouts, are
+    /// transient; errors building the request, such as an invalid url, are not.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            EvgError::Transport(err) => err.is_timeout() || err.is_connect() || err.is_request(),
+            EvgError::HttpStatus { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if this error indicates the requested object does not exist.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, EvgError::NotFound { .. })
+    }
+}
+
+impl fmt::Display for EvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvgError::Config { message, source } => match source {
+                Some(source) => write!(f, "configuration error: {}: {}", message, source),
+                None => write!(f, "configuration error: {}", message),
+            },
+            EvgError::Transport(err) => write!(f, "transport error: {}", err),
+            EvgError::HttpStatus { url, status, body } => {
+                write!(f, "request to '{}' failed with {}: {}", url, status, body)
+            }
+            EvgError::Deserialize {
+                url,
+                snippet,
+                source,
+            } => write!(
+                f,
+                "could not deserialize response from '{}': {} (payload: '{}')",
+                url, source, snippet
+            ),
+            EvgError::NotFound { url } => write!(f, "'{}' was not found", url),
+            EvgError::MissingLog { task_id, log_name } => {
+                write!(f, "task '{}' has no '{}' log", task_id, log_name)
+            }
+            EvgError::WaitTimeout { elapsed } => {
+                write!(f, "gave up waiting after {:.1}s", elapsed.as_secs_f64())
+            }
+            EvgError::Cassette { message, source } => match source {
+                Some(source) => write!(f, "cassette error: {}: {}", message, source),
+                None => write!(f, "cassette error: {}", message),
+            },
+        }
+    }
+}
+
+impl Error for EvgError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EvgError::Config {
+                source: Some(source),
+                ..
+            }
+            | EvgError::Cassette {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            EvgError::Transport(err) => Some(err),
+            EvgError::Deserialize { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for EvgError {
+    fn from(err: reqwest::Error) -> Self {
+        EvgError::Transport(err)
+    }
+}
@@ -0,0 +1,258 @@
+use crate::action;
+use crate::models::ids::{TaskId, VersionId};
+use crate::models::task::{EvgTask, EvgTaskDependency};
+use crate::{EvgApiClient, EvgError};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+/// Dependency graph of a set of tasks, usually all the tasks of a version.
+///
+/// Dependencies on tasks outside the graph are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct TaskGraph {
+    tasks: BTreeMap<TaskId, EvgTask>,
+    dependents: HashMap<TaskId, Vec<TaskId>>,
+}
+
+impl TaskGraph {
+    /// Build the graph of the given tasks.
+    pub fn new(tasks: impl IntoIterator<Item = EvgTask>) -> Self {
+        let tasks: BTreeMap<TaskId, EvgTask> =
+            tasks.into_iter().map(|t| (t.task_id.clone(), t)).collect();
+        let mut dependents: HashMap<TaskId, Vec<TaskId>> = HashMap::new();
+        for task in tasks.values() {
+            for dependency in task.depends_on.iter().flatten() {
+                if tasks.contains_key(dependency.task_id()) {
+                    dependents
+                        .entry(dependency.task_id().clone())
+                        .or_default()
+                        .push(task.task_id.clone());
+                }
+            }
+        }
+        Self { tasks, dependents }
+    }
+
+    /// Build the graph of all tasks in all builds of the given version.
+    pub async fn for_version<C>(client: &C, version_id: &VersionId) -> Result<Self, EvgError>
+    where
+        C: EvgApiClient + ?Sized,
+    {
+        Ok(Self::new(action::version_tasks(client, version_id).await?))
+    }
+
+    /// The task with the given id.
+    pub fn task(&self, task_id: &TaskId) -> Option<&EvgTask> {
+        self.tasks.get(task_id)
+    }
+
+    /// All tasks in the graph, ordered by id.
+    pub fn tasks(&self) -> impl Iterator<Item = &EvgTask> {
+        self.tasks.values()
+    }
+
+    /// Tasks the given task depends on, directly or transitively, nearest first.
+    pub fn upstream(&self, task_id: &TaskId) -> Vec<&EvgTask> {
+        self.walk(task_id, |id| {
+            self.dependencies(id).map(|d| d.task_id().clone()).collect()
+        })
+    }
+
+    /// Tasks depending on the given task, directly or transitively, nearest first.
+    pub fn downstream(&self, task_id: &TaskId) -> Vec<&EvgTask> {
+        self.walk(task_id, |id| {
+            self.dependents.get(id).cloned().unwrap_or_default()
+        })
+    }
+
+    /// Map each finished task whose status does not satisfy its dependents to the unfinished
+    /// tasks it is blocking, directly or transitively.
+    pub fn blocked_by_failures(&self) -> BTreeMap<TaskId, Vec<TaskId>> {
+        let mut blocked = BTreeMap::new();
+        for task in self.tasks.values().filter(|t| t.status.is_finished()) {
+            let blocking_dependents = self.next_blocked(task);
+            if blocking_dependents.is_empty() {
+                continue;
+            }
+
+            let mut seen: HashSet<TaskId> = HashSet::new();
+            let mut queue: VecDeque<TaskId> = blocking_dependents.into();
+            let mut blocked_tasks = vec![];
+            while let Some(id) = queue.pop_front() {
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+                if let Some(dependent) = self.tasks.get(&id) {
+                    queue.extend(self.next_blocked(dependent));
+                }
+                blocked_tasks.push(id);
+            }
+            blocked.insert(task.task_id.clone(), blocked_tasks);
+        }
+        blocked
+    }
+
+    /// Render the graph in Graphviz DOT format, with edges pointing from a dependency to its
+    /// dependents.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph tasks {\n");
+        for task in self.tasks.values() {
+            let color = if task.status.is_success() {
+                "green"
+            } else if task.status.is_failure() {
+                "red"
+            } else {
+                "gray"
+            };
+            writeln!(
+                dot,
+                "  \"{}\" [label=\"{}\\n{}\", color={}];",
+                escape(task.task_id.as_str()),
+                escape(&task.display_name),
+                task.status,
+                color
+            )
+            .unwrap();
+        }
+        for task in self.tasks.values() {
+            for dependency in self.dependencies(&task.task_id) {
+                let label = match dependency.required_status() {
+                    Some(status) => format!(" [label=\"{}\"]", escape(status)),
+                    None => String::new(),
+                };
+                writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\"{};",
+                    escape(dependency.task_id().as_str()),
+                    escape(task.task_id.as_str()),
+                    label
+                )
+                .unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Dependencies of the given task that are part of the graph.
+    fn dependencies<'a>(
+        &'a self,
+        task_id: &TaskId,
+    ) -> impl Iterator<Item = &'a EvgTaskDependency> + 'a {
+        self.tasks
+            .get(task_id)
+            .and_then(|t| t.depends_on.as_ref())
+            .into_iter()
+            .flatten()
+            .filter(move |d| self.tasks.contains_key(d.task_id()))
+    }
+
+    /// Unfinished dependents that cannot run because of the state of the given task.
+    fn next_blocked(&self, task: &EvgTask) -> Vec<TaskId> {
+        let dependents = self.dependents.get(&task.task_id).into_iter().flatten();
+        dependents
+            .filter_map(|id| self.tasks.get(id))
+            .filter(|dependent| !dependent.status.is_finished())
+            .filter(|dependent| {
+                !task.status.is_finished()
+                    || self
+                        .dependencies(&dependent.task_id)
+                        .any(|d| d.task_id() == &task.task_id && !d.is_satisfied_by(&task.status))
+            })
+            .map(|dependent| dependent.task_id.clone())
+            .collect()
+    }
+
+    /// Breadth first walk from the given task, excluding it.
+    fn walk<F>(&self, task_id: &TaskId, next: F) -> Vec<&EvgTask>
+    where
+        F: Fn(&TaskId) -> Vec<TaskId>,
+    {
+        let mut seen: HashSet<TaskId> = HashSet::new();
+        seen.insert(task_id.clone());
+        let mut queue: VecDeque<TaskId> = next(task_id).into();
+        let mut found = vec![];
+        while let Some(id) = queue.pop_front() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            queue.extend(next(&id));
+            if let Some(task) = self.tasks.get(&id) {
+                found.push(task);
+            }
+        }
+        found
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::{self, task_json};
+
+    fn graph() -> TaskGraph {
+        let tasks = vec![
+            ("compile", "failed", serde_json::json!(null)),
+            ("lint", "success", serde_json::json!([])),
+            ("unit", "undispatched", serde_json::json!(["compile"])),
+            (
+                "integration",
+                "undispatched",
+                serde_json::json!([{ "id": "unit", "status": "success" }, "lint"]),
+            ),
+            (
+                "cleanup",
+                "undispatched",
+                serde_json::json!([{ "id": "compile", "status": "*" }]),
+            ),
+        ];
+        TaskGraph::new(tasks.into_iter().map(|(task_id, status, depends_on)| {
+            let mut json = task_json(task_id, "build", status);
+            json["depends_on"] = depends_on;
+            fixtures::parse(json)
+        }))
+    }
+
+    fn ids(tasks: Vec<&EvgTask>) -> Vec<&str> {
+        tasks.iter().map(|t| t.task_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_graph_should_answer_upstream_and_downstream_queries() {
+        let graph = graph();
+
+        assert_eq!(
+            ids(graph.upstream(&"integration".into())),
+            vec!["unit", "lint", "compile"]
+        );
+        assert_eq!(
+            ids(graph.downstream(&"compile".into())),
+            vec!["cleanup", "unit", "integration"]
+        );
+    }
+
+    #[test]
+    fn test_blocked_by_failures_should_respect_required_status() {
+        let blocked = graph().blocked_by_failures();
+
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(
+            blocked[&TaskId::from("compile")],
+            vec!["unit", "integration"]
+        );
+    }
+
+    #[test]
+    fn test_to_dot_should_include_nodes_and_edges() {
+        let dot = graph().to_dot();
+
+        assert!(dot.starts_with("digraph tasks {"));
+        assert!(dot.contains("\"compile\" [label=\"compile\\nfailed\", color=red];"));
+        assert!(dot.contains("\"compile\" -> \"cleanup\" [label=\"*\"];"));
+        assert!(dot.contains("\"lint\" -> \"integration\";"));
+    }
+}
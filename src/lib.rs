@@ -1,9 +1,32 @@
+mod action;
+mod builder;
+mod cache;
+mod cassette;
+mod config;
+mod display;
+mod error;
+mod graph;
+mod limit;
 pub mod models;
+mod retry;
+mod stream;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod tree;
+mod watch;
 
-use async_stream::stream;
+use async_stream::try_stream;
 use async_trait::async_trait;
+use cassette::Cassette;
+use config::{discover_config, env_var, get_evg_config, EvergreenConfig};
 use futures::stream::Stream;
 use futures::stream::StreamExt;
+use models::commit_queue::{EvgCommitQueue, EvgCommitQueuePosition};
+use models::distro::{EvgDistro, EvgTaskQueueItem};
+use models::host::{EvgHost, EvgHostEvent, EvgHostFilter};
+use models::ids::{BuildId, DistroId, HostId, PatchId, ProjectId, TaskId, VersionId};
+use models::patch::EvgPatchConfiguration;
+use models::project::{EvgProject, EvgProjectAlias, EvgProjectVariables};
 use models::stats::EvgTaskStats;
 use models::stats::EvgTaskStatsRequest;
 use models::stats::EvgTestStats;
@@ -12,142 +35,439 @@ use models::version::EvgVersion;
 use models::{build::EvgBuild, patch::EvgPatch};
 use models::{task::EvgTask, test::EvgTest};
 use reqwest::{
-    header::{HeaderMap, HeaderValue, LINK},
-    Client, Response,
+    header::{HeaderMap, LINK},
+    Client, Method, Request, RequestBuilder, Response, StatusCode,
 };
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use std::path::Path;
 use std::pin::Pin;
-use std::{error::Error, fs};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
-pub type BoxedStream<T> = Pin<Box<dyn Stream<Item = T>>>;
-pub type EvgError = Box<dyn Error + Sync + Send>;
-
-const DEFAULT_CONFIG_FILE: &str = ".evergreen.yml";
-
-#[derive(Debug, Deserialize, Clone)]
-struct EvergreenConfigFile {
-    pub user: String,
-    pub api_key: String,
-    pub api_server_host: String,
-}
+pub use action::ActionReport;
+pub use builder::EvgClientBuilder;
+pub use cache::CachingEvgClient;
+pub use display::{DisplayTaskTree, ExecutionTaskNode};
+pub use error::EvgError;
+pub use graph::TaskGraph;
+pub use limit::RateLimiter;
+pub use retry::RetryPolicy;
+pub use stream::EvgStreamExt;
+pub use tree::{BuildNode, TaskNode, TreeDepth, TreeFetchFailure, VersionTree};
+pub use watch::{
+    wait_for_build, wait_for_patch, wait_for_task, wait_for_version, watch_commit_queue,
+    CommitQueueChange, WaitOptions, WaitProgress,
+};
 
-fn get_evg_config(path: &Path) -> Result<EvergreenConfigFile, EvgError> {
-    let contents = fs::read_to_string(&path)?;
-    let evg_config: EvergreenConfigFile = serde_yaml::from_str(&contents)?;
-    Ok(evg_config)
-}
+/// A boxed stream that can be sent between threads.
+pub type BoxedStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+/// A stream of results from the Evergreen API. The stream ends after yielding an error.
+pub type EvgStream<T> = BoxedStream<Result<T, EvgError>>;
 
 #[async_trait]
 pub trait EvgApiClient: Sync + Send {
     /// Get details about the given task.
-    async fn get_task(&self, task_id: &str) -> Result<EvgTask, EvgError>;
+    async fn get_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError>;
+    /// Get details about the given execution of a task.
+    async fn get_task_execution(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<EvgTask, EvgError>;
+    /// Stream every execution of the given task, from the first run to the latest.
+    fn stream_task_executions(&self, task_id: &TaskId) -> EvgStream<EvgTask>;
+    /// Restart the given task, or only its failed execution tasks if it is a display task and
+    /// `failed_only` is set.
+    async fn restart_task(&self, task_id: &TaskId, failed_only: bool) -> Result<EvgTask, EvgError>;
+    /// Abort the given task.
+    async fn abort_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError>;
+    /// Schedule or unschedule the given task.
+    async fn set_task_activated(
+        &self,
+        task_id: &TaskId,
+        activated: bool,
+    ) -> Result<EvgTask, EvgError>;
+    /// Set the priority of the given task.
+    async fn set_task_priority(&self, task_id: &TaskId, priority: i64)
+        -> Result<EvgTask, EvgError>;
+    /// Fetch the execution tasks of the given display task along with their tests.
+    async fn expand_display_task(&self, task: &EvgTask) -> Result<DisplayTaskTree, EvgError> {
+        display::expand(self, task).await
+    }
     /// Get details about the given version.
-    async fn get_version(&self, version_id: &str) -> Result<EvgVersion, EvgError>;
+    async fn get_version(&self, version_id: &VersionId) -> Result<EvgVersion, EvgError>;
+    /// Fetch the given version along with its builds, tasks and tests down to `depth`, with at
+    /// most `concurrency` requests in flight. Parts that fail to load are reported in the tree.
+    async fn fetch_version_tree(
+        &self,
+        version_id: &VersionId,
+        depth: TreeDepth,
+        concurrency: usize,
+    ) -> Result<VersionTree, EvgError> {
+        tree::fetch(self, version_id, depth, concurrency).await
+    }
     /// Get details about the given build.
-    async fn get_build(&self, build_id: &str) -> Result<Option<EvgBuild>, EvgError>;
+    async fn get_build(&self, build_id: &BuildId) -> Result<Option<EvgBuild>, EvgError>;
+    /// Schedule or unschedule the tasks of the given build.
+    async fn activate_build(
+        &self,
+        build_id: &BuildId,
+        activated: bool,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError>;
+    /// Restart the failed tasks of the given build, one by one. Tasks that could not be
+    /// restarted are listed in the report's `failed_tasks`.
+    async fn restart_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError>;
+    /// Abort the running and scheduled tasks of the given build.
+    async fn abort_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError>;
+    /// Set the priority of the unfinished tasks of the given build.
+    async fn set_build_priority(
+        &self,
+        build_id: &BuildId,
+        priority: i64,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError>;
+    /// Restart the failed tasks of the given version, one by one. Tasks that could not be
+    /// restarted are listed in the report's `failed_tasks`.
+    async fn restart_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError>;
+    /// Abort the running and scheduled tasks of the given version.
+    async fn abort_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError>;
     /// Get the tests belonging to the given task.
-    async fn get_tests(&self, task_id: &str) -> Result<Vec<EvgTest>, EvgError>;
+    async fn get_tests(&self, task_id: &TaskId) -> Result<Vec<EvgTest>, EvgError>;
+    /// Get the tests run by the given execution of a task.
+    async fn get_execution_tests(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<Vec<EvgTest>, EvgError>;
     /// Get test stats for the given query.
     async fn get_test_stats(
         &self,
-        project_id: &str,
+        project_id: &ProjectId,
         query: &EvgTestStatsRequest,
     ) -> Result<Vec<EvgTestStats>, EvgError>;
     /// Get task stats for the given query.
     async fn get_task_stats(
         &self,
-        project_id: &str,
+        project_id: &ProjectId,
         query: &EvgTaskStatsRequest,
     ) -> Result<Vec<EvgTaskStats>, EvgError>;
+    /// Get details about the given patch.
+    async fn get_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError>;
+    /// Abort all running and scheduled tasks of the given patch.
+    async fn abort_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError>;
+    /// Restart all tasks of the given patch.
+    async fn restart_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError>;
+    /// Set the priority of all tasks of the given patch.
+    async fn set_patch_priority(
+        &self,
+        patch_id: &PatchId,
+        priority: i64,
+    ) -> Result<EvgPatch, EvgError>;
+    /// Schedule or unschedule the tasks of the given patch.
+    async fn activate_patch(
+        &self,
+        patch_id: &PatchId,
+        activated: bool,
+    ) -> Result<EvgPatch, EvgError>;
+    /// Select the variants and tasks to run for the given patch and finalize it.
+    async fn configure_patch(
+        &self,
+        patch_id: &PatchId,
+        configuration: &EvgPatchConfiguration,
+    ) -> Result<EvgPatch, EvgError>;
+    /// List all projects visible to the user.
+    async fn list_projects(&self) -> Result<Vec<EvgProject>, EvgError>;
+    /// Get the settings of the given project.
+    async fn get_project(&self, project_id: &ProjectId) -> Result<EvgProject, EvgError>;
+    /// Get the variables of the given project, with the values of private variables masked.
+    async fn get_project_variables(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<EvgProjectVariables, EvgError>;
+    /// Get the patch aliases defined for the given project.
+    async fn get_project_aliases(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<Vec<EvgProjectAlias>, EvgError>;
+    /// Get details about the given host.
+    async fn get_host(&self, host_id: &HostId) -> Result<EvgHost, EvgError>;
+    /// Get the host the given task ran on, if it was dispatched to one.
+    async fn get_task_host(&self, task: &EvgTask) -> Result<Option<EvgHost>, EvgError> {
+        if task.host_id.as_str().is_empty() {
+            return Ok(None);
+        }
+        self.get_host(&task.host_id).await.map(Some)
+    }
+    /// Get the event log of the given host.
+    async fn get_host_events(&self, host_id: &HostId) -> Result<Vec<EvgHostEvent>, EvgError>;
+    /// List all distros.
+    async fn list_distros(&self) -> Result<Vec<EvgDistro>, EvgError>;
+    /// Get the settings of the given distro.
+    async fn get_distro(&self, distro_id: &DistroId) -> Result<EvgDistro, EvgError>;
+    /// Stream the hosts matching the given filter.
+    fn stream_hosts(&self, filter: &EvgHostFilter) -> EvgStream<EvgHost>;
+    /// Stream the tasks waiting in the queue of the given distro, in dispatch order.
+    fn stream_distro_queue(&self, distro_id: &DistroId) -> EvgStream<EvgTaskQueueItem>;
+    /// Get the commit queue of the given project.
+    async fn get_commit_queue(&self, project_id: &ProjectId) -> Result<EvgCommitQueue, EvgError>;
+    /// Add the given patch to its project's commit queue, or to the front of it if `force` is
+    /// set, returning its zero-based position.
+    async fn enqueue_patch(&self, patch_id: &PatchId, force: bool) -> Result<usize, EvgError>;
+    /// Remove the given patch from the commit queue of the given project.
+    async fn dequeue_patch(
+        &self,
+        project_id: &ProjectId,
+        patch_id: &PatchId,
+    ) -> Result<(), EvgError>;
+    /// Get the zero-based position of the given patch in its project's commit queue, if queued.
+    async fn get_commit_queue_position(&self, patch: &EvgPatch) -> Result<Option<usize>, EvgError> {
+        let queue = self.get_commit_queue(&patch.project_id).await?;
+        Ok(queue.position(&patch.patch_id))
+    }
     /// Stream version of an evergreen project.
-    fn stream_versions(&self, project_id: &str) -> BoxedStream<EvgVersion>;
+    fn stream_versions(&self, project_id: &ProjectId) -> EvgStream<EvgVersion>;
     /// Stream user patches of an evergreen project.
-    fn stream_user_patches(&self, user_id: &str, limit: Option<usize>) -> BoxedStream<EvgPatch>;
+    fn stream_user_patches(&self, user_id: &str, limit: Option<usize>) -> EvgStream<EvgPatch>;
     /// Stream patches of an evergreen project.
     fn stream_project_patches(
         &self,
-        project_id: &str,
+        project_id: &ProjectId,
         limit: Option<usize>,
-    ) -> BoxedStream<EvgPatch>;
+    ) -> EvgStream<EvgPatch>;
     /// Stream tasks of an evergreen build.
-    fn stream_build_tasks(&self, build_id: &str, status: Option<&str>) -> BoxedStream<EvgTask>;
+    fn stream_build_tasks(&self, build_id: &BuildId, status: Option<&str>) -> EvgStream<EvgTask>;
     /// Stream the contents of a task level log.
-    fn stream_log(&self, task: &EvgTask, log_name: &str) -> BoxedStream<String>;
+    fn stream_log(&self, task: &EvgTask, log_name: &str) -> EvgStream<String>;
+    /// Stream the contents of a task level log of the given execution of a task.
+    fn stream_execution_log(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+        log_name: &str,
+    ) -> EvgStream<String>;
     /// Stream the contents of a test level log.
-    fn stream_test_log(&self, test: &EvgTest) -> BoxedStream<String>;
+    fn stream_test_log(&self, test: &EvgTest) -> EvgStream<String>;
 }
 
 #[derive(Clone)]
 pub struct EvgClient {
-    evg_config: EvergreenConfigFile,
+    api_server_host: String,
     client: Client,
+    headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    in_flight: Option<Arc<Semaphore>>,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl EvgClient {
-    /// Create a new EvgClient based on the default evergreen auth file location (~/.evergreen.yml).
+    /// Create a new EvgClient from the first configuration source found.
+    ///
+    /// The following sources are checked in order:
+    /// * The file named by the `EVERGREEN_CONFIG` environment variable.
+    /// * The `EVG_API_USER`, `EVG_API_KEY` and `EVG_API_SERVER_HOST` environment variables.
+    /// * `$XDG_CONFIG_HOME/evergreen.yml`.
+    /// * `$HOME/.evergreen.yml`.
     pub fn new() -> Result<EvgClient, EvgError> {
-        let home = std::env::var("HOME")?;
-        let path = format!("{}/{}", home, DEFAULT_CONFIG_FILE);
-        Self::from_file(Path::new(&path))
+        let evg_config = discover_config(env_var, None)?;
+        Self::from_config(&evg_config)
+    }
+
+    /// Create a new EvgClient using the named profile of the first evergreen config file found.
+    ///
+    /// Config files are searched for in the same locations as `EvgClient::new`.
+    pub fn from_profile(profile_name: &str) -> Result<EvgClient, EvgError> {
+        let evg_config = discover_config(env_var, Some(profile_name))?;
+        Self::from_config(&evg_config)
     }
 
     /// Create a new EvgClient based on the evergreen auth file at the provided location.
     pub fn from_file(config_file: &Path) -> Result<EvgClient, EvgError> {
-        let evg_config = get_evg_config(config_file)?;
-        let mut headers = HeaderMap::new();
-        headers.insert("Api-User", HeaderValue::from_str(&evg_config.user)?);
-        headers.insert("Api-Key", HeaderValue::from_str(&evg_config.api_key)?);
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?;
-
-        Ok(EvgClient { evg_config, client })
+        let evg_config = get_evg_config(config_file, None)?;
+        Self::from_config(&evg_config)
     }
 
-    fn build_url(&self, endpoint: &str, arg: &str) -> String {
-        format!(
-            "{}/rest/v2/{}/{}",
-            self.evg_config.api_server_host, endpoint, arg
+    fn from_config(evg_config: &EvergreenConfig) -> Result<EvgClient, EvgError> {
+        Self::builder(
+            &evg_config.api_server_host,
+            &evg_config.user,
+            &evg_config.api_key,
         )
+        .build()
     }
-}
 
-#[async_trait]
-impl EvgApiClient for EvgClient {
-    async fn get_task(&self, task_id: &str) -> Result<EvgTask, EvgError> {
-        let url = self.build_url("tasks", task_id);
-        let response = self.client.get(&url).send().await?;
-        Ok(response.json().await?)
+    /// Create a builder for an EvgClient using the given server and credentials.
+    pub fn builder(api_server_host: &str, user: &str, api_key: &str) -> EvgClientBuilder {
+        EvgClientBuilder::new(api_server_host, user, api_key)
+    }
+
+    /// Use the given policy to retry failed requests.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limit the rate at which requests are started. The limit is shared by all clones of
+    /// this client.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
     }
 
-    async fn get_version(&self, version_id: &str) -> Result<EvgVersion, EvgError> {
-        let url = self.build_url("versions", version_id);
-        let response = self.client.get(&url).send().await?;
-        Ok(response.json().await?)
+    /// Limit the number of requests that can be in flight at once. The limit is shared by all
+    /// clones of this client.
+    ///
+    /// A request counts against the limit until its response headers arrive; response bodies,
+    /// such as streamed logs, are read outside of it. The limit must be at least 1.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Result<Self, EvgError> {
+        self.in_flight = Some(in_flight_limit(max_in_flight)?);
+        Ok(self)
     }
 
-    async fn get_build(&self, build_id: &str) -> Result<Option<EvgBuild>, EvgError> {
-        let url = self.build_url("builds", build_id);
-        let response = self.client.get(&url).send().await?;
-        if response.status() == 404 {
-            Ok(None)
-        } else {
-            Ok(Some(response.json().await?))
+    /// Record every request made by this client, along with its response, to a cassette file at
+    /// the given path. The `Api-Key` header and the values of private project variables are
+    /// redacted from the recording.
+    pub fn record_to(mut self, path: &Path) -> Result<Self, EvgError> {
+        self.cassette = Some(Arc::new(Cassette::record(path)?));
+        Ok(self)
+    }
+
+    /// Answer every request made by this client from the cassette file at the given path,
+    /// without any network access.
+    pub fn replay_from(mut self, path: &Path) -> Result<Self, EvgError> {
+        self.cassette = Some(Arc::new(Cassette::replay(path)?));
+        Ok(self)
+    }
+
+    /// Send the given request, retrying it as described by the client's retry policy.
+    ///
+    /// Only GET and HEAD requests are retried after errors and failure statuses. Other requests
+    /// may already have been applied when their response is lost, so they are only resent when
+    /// the server rejected them with a `Retry-After` header.
+    async fn send(&self, request: RequestBuilder) -> Result<Response, EvgError> {
+        let request = request.build()?;
+        let replayable = matches!(*request.method(), Method::GET | Method::HEAD);
+        let mut attempt = 1;
+        loop {
+            let pending = request
+                .try_clone()
+                .expect("requests without a streaming body can be cloned");
+            let delay = match self.send_limited(pending).await {
+                Ok(response) => {
+                    let delay = if replayable {
+                        self.retry_policy.retry_response(attempt, &response)
+                    } else {
+                        self.retry_policy.retry_rejected(attempt, &response)
+                    };
+                    match delay {
+                        Some(delay) => delay,
+                        None => return Ok(response),
+                    }
+                }
+                Err(err) if replayable => match self.retry_policy.retry_error(attempt, &err) {
+                    Some(delay) => delay,
+                    None => return Err(err),
+                },
+                Err(err) => return Err(err),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send a single request once the rate limiter and in-flight limit allow it.
+    ///
+    /// The in-flight permit is released once the response headers arrive, so reading a large
+    /// or streamed body does not hold up other requests.
+    ///
+    /// When a cassette is attached the request is recorded to it, or answered from it in
+    /// replay mode.
+    async fn send_limited(&self, request: Request) -> Result<Response, EvgError> {
+        if let Some(cassette) = &self.cassette {
+            if cassette.is_replay() {
+                return cassette.play(&request);
+            }
         }
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let recorded = self
+            .cassette
+            .as_ref()
+            .map(|_| request.try_clone().expect("requests can be cloned"));
+        let response = {
+            let _permit = match &self.in_flight {
+                Some(in_flight) => Some(
+                    in_flight
+                        .acquire()
+                        .await
+                        .expect("in-flight semaphore is never closed"),
+                ),
+                None => None,
+            };
+            self.client.execute(request).await?
+        };
+        match (&self.cassette, recorded) {
+            (Some(cassette), Some(recorded)) => cassette.store(&recorded, response).await,
+            _ => Ok(response),
+        }
+    }
+
+    /// Send a request whose response body is not needed.
+    async fn send_action(&self, request: RequestBuilder) -> Result<(), EvgError> {
+        check_response(self.send(request).await?).await?;
+        Ok(())
     }
 
-    async fn get_tests(&self, task_id: &str) -> Result<Vec<EvgTest>, EvgError> {
-        let url = format!("{}/tests", self.build_url("tasks", task_id));
-        let mut results: Vec<EvgTest> = vec![];
-        let mut response = self.client.get(&url).send().await?;
+    /// Start a GET request to the given url carrying the client's credentials.
+    fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Start a request with the given method to the given url carrying the client's credentials.
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .headers(self.headers.clone())
+    }
+
+    fn build_url(&self, endpoint: &str, arg: &str) -> String {
+        format!("{}/rest/v2/{}/{}", self.api_server_host, endpoint, arg)
+    }
+
+    /// Fetch all items of a paginated endpoint starting with the given request, following the
+    /// `Link` header between pages.
+    async fn collect_pages<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<Vec<T>, EvgError> {
+        let mut results: Vec<T> = vec![];
+        let mut response = self.send(request).await?;
         loop {
             let next_link = next_link(&response);
-            let result_batch: Vec<EvgTest> = response.json().await?;
+            let result_batch: Vec<T> = parse_response(response).await?;
             results.extend(result_batch);
 
             if let Some(next) = next_link {
-                response = self.client.get(&next).send().await?;
+                response = self.send(self.get(&next)).await?;
             } else {
                 break;
             }
@@ -155,173 +475,558 @@ impl EvgApiClient for EvgClient {
         Ok(results)
     }
 
+    /// Stream the items of a paginated endpoint, following the `Link` header between pages.
+    ///
+    /// The stream ends after yielding the first error it encounters.
+    fn paginate<T: DeserializeOwned + Send + 'static>(&self, url: String) -> EvgStream<T> {
+        let evg_client = self.clone();
+
+        Box::pin(try_stream! {
+            let mut next = Some(url);
+            while let Some(url) = next {
+                let response = evg_client.send(evg_client.get(&url)).await?;
+                next = next_link(&response);
+                let result_batch: Vec<T> = parse_response(response).await?;
+                for item in result_batch {
+                    yield item;
+                }
+            }
+        })
+    }
+
+    /// Stream the contents of the text log at the given url line by line.
+    ///
+    /// The stream ends after yielding the first error it encounters.
+    fn stream_lines(&self, url: String) -> EvgStream<String> {
+        let evg_client = self.clone();
+
+        Box::pin(try_stream! {
+            let response = evg_client.send(evg_client.get(&url)).await?;
+            let response = check_response(response).await?;
+            let lines = split_lines(response.bytes_stream());
+            futures::pin_mut!(lines);
+            while let Some(line) = lines.next().await {
+                yield line?;
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl EvgApiClient for EvgClient {
+    async fn get_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError> {
+        let url = self.build_url("tasks", task_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn get_task_execution(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<EvgTask, EvgError> {
+        let url = self.build_url("tasks", task_id.as_str());
+        let response = self
+            .send(self.get(&url).query(&[("execution", execution)]))
+            .await?;
+        parse_response(response).await
+    }
+
+    fn stream_task_executions(&self, task_id: &TaskId) -> EvgStream<EvgTask> {
+        let client = self.clone();
+        let task_id = task_id.clone();
+        Box::pin(try_stream! {
+            let latest = client.get_task(&task_id).await?;
+            for execution in 0..latest.execution {
+                yield client.get_task_execution(&task_id, execution).await?;
+            }
+            yield latest;
+        })
+    }
+
+    async fn restart_task(&self, task_id: &TaskId, failed_only: bool) -> Result<EvgTask, EvgError> {
+        let url = format!("{}/restart", self.build_url("tasks", task_id.as_str()));
+        let body = serde_json::json!({ "failed_only": failed_only });
+        let response = self
+            .send(self.request(Method::POST, &url).json(&body))
+            .await?;
+        parse_response(response).await
+    }
+
+    async fn abort_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError> {
+        let url = format!("{}/abort", self.build_url("tasks", task_id.as_str()));
+        let response = self.send(self.request(Method::POST, &url)).await?;
+        parse_response(response).await
+    }
+
+    async fn set_task_activated(
+        &self,
+        task_id: &TaskId,
+        activated: bool,
+    ) -> Result<EvgTask, EvgError> {
+        let url = self.build_url("tasks", task_id.as_str());
+        let body = serde_json::json!({ "activated": activated });
+        let response = self
+            .send(self.request(Method::PATCH, &url).json(&body))
+            .await?;
+        parse_response(response).await
+    }
+
+    async fn set_task_priority(
+        &self,
+        task_id: &TaskId,
+        priority: i64,
+    ) -> Result<EvgTask, EvgError> {
+        let url = self.build_url("tasks", task_id.as_str());
+        let body = serde_json::json!({ "priority": priority });
+        let response = self
+            .send(self.request(Method::PATCH, &url).json(&body))
+            .await?;
+        parse_response(response).await
+    }
+
+    async fn get_version(&self, version_id: &VersionId) -> Result<EvgVersion, EvgError> {
+        let url = self.build_url("versions", version_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn get_build(&self, build_id: &BuildId) -> Result<Option<EvgBuild>, EvgError> {
+        let url = self.build_url("builds", build_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        match parse_response(response).await {
+            Ok(build) => Ok(Some(build)),
+            Err(EvgError::NotFound { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn activate_build(
+        &self,
+        build_id: &BuildId,
+        activated: bool,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let affected_tasks = action::build_tasks_matching(self, build_id, |t| {
+            action::is_activation_affected(t, activated)
+        })
+        .await?;
+        if !dry_run {
+            let url = self.build_url("builds", build_id.as_str());
+            let body = serde_json::json!({ "activated": activated });
+            self.send_action(self.request(Method::PATCH, &url).json(&body))
+                .await?;
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn restart_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let affected_tasks =
+            action::build_tasks_matching(self, build_id, action::is_restart_affected).await?;
+        if dry_run {
+            return Ok(ActionReport::new(dry_run, affected_tasks));
+        }
+        Ok(action::restart_tasks(self, affected_tasks).await)
+    }
+
+    async fn abort_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let affected_tasks =
+            action::build_tasks_matching(self, build_id, action::is_abort_affected).await?;
+        if !dry_run {
+            let url = format!("{}/abort", self.build_url("builds", build_id.as_str()));
+            self.send_action(self.request(Method::POST, &url)).await?;
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn set_build_priority(
+        &self,
+        build_id: &BuildId,
+        priority: i64,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let affected_tasks =
+            action::build_tasks_matching(self, build_id, action::is_priority_affected).await?;
+        if !dry_run {
+            let url = self.build_url("builds", build_id.as_str());
+            let body = serde_json::json!({ "priority": priority });
+            self.send_action(self.request(Method::PATCH, &url).json(&body))
+                .await?;
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn restart_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let affected_tasks =
+            action::version_tasks_matching(self, version_id, action::is_restart_affected).await?;
+        if dry_run {
+            return Ok(ActionReport::new(dry_run, affected_tasks));
+        }
+        Ok(action::restart_tasks(self, affected_tasks).await)
+    }
+
+    async fn abort_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        let affected_tasks =
+            action::version_tasks_matching(self, version_id, action::is_abort_affected).await?;
+        if !dry_run {
+            let url = format!("{}/abort", self.build_url("versions", version_id.as_str()));
+            self.send_action(self.request(Method::POST, &url)).await?;
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn get_tests(&self, task_id: &TaskId) -> Result<Vec<EvgTest>, EvgError> {
+        let url = format!("{}/tests", self.build_url("tasks", task_id.as_str()));
+        self.collect_pages(self.get(&url)).await
+    }
+
+    async fn get_execution_tests(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<Vec<EvgTest>, EvgError> {
+        let url = format!("{}/tests", self.build_url("tasks", task_id.as_str()));
+        let request = self.get(&url).query(&[("execution", execution)]);
+        self.collect_pages(request).await
+    }
+
     async fn get_test_stats(
         &self,
-        project_id: &str,
+        project_id: &ProjectId,
         query: &EvgTestStatsRequest,
     ) -> Result<Vec<EvgTestStats>, EvgError> {
-        let url = format!("{}/test_stats", self.build_url("projects", project_id));
-        let response = self.client.get(&url).query(query).send().await?;
-
-        Ok(response.json().await?)
+        let url = format!(
+            "{}/test_stats",
+            self.build_url("projects", project_id.as_str())
+        );
+        let response = self.send(self.get(&url).query(query)).await?;
+        parse_response(response).await
     }
 
     async fn get_task_stats(
         &self,
-        project_id: &str,
+        project_id: &ProjectId,
         query: &EvgTaskStatsRequest,
     ) -> Result<Vec<EvgTaskStats>, EvgError> {
-        let url = format!("{}/task_stats", self.build_url("projects", project_id));
-        let response = self.client.get(&url).query(query).send().await?;
-        Ok(response.json().await?)
+        let url = format!(
+            "{}/task_stats",
+            self.build_url("projects", project_id.as_str())
+        );
+        let response = self.send(self.get(&url).query(query)).await?;
+        parse_response(response).await
+    }
+
+    async fn get_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        let url = self.build_url("patches", patch_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn abort_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        let url = format!("{}/abort", self.build_url("patches", patch_id.as_str()));
+        let response = self.send(self.request(Method::POST, &url)).await?;
+        parse_response(response).await
+    }
+
+    async fn restart_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        let url = format!("{}/restart", self.build_url("patches", patch_id.as_str()));
+        let response = self.send(self.request(Method::POST, &url)).await?;
+        parse_response(response).await
+    }
+
+    async fn set_patch_priority(
+        &self,
+        patch_id: &PatchId,
+        priority: i64,
+    ) -> Result<EvgPatch, EvgError> {
+        let url = self.build_url("patches", patch_id.as_str());
+        let body = serde_json::json!({ "priority": priority });
+        let response = self
+            .send(self.request(Method::PATCH, &url).json(&body))
+            .await?;
+        parse_response(response).await
+    }
+
+    async fn activate_patch(
+        &self,
+        patch_id: &PatchId,
+        activated: bool,
+    ) -> Result<EvgPatch, EvgError> {
+        let url = self.build_url("patches", patch_id.as_str());
+        let body = serde_json::json!({ "activated": activated });
+        let response = self
+            .send(self.request(Method::PATCH, &url).json(&body))
+            .await?;
+        parse_response(response).await
+    }
+
+    async fn configure_patch(
+        &self,
+        patch_id: &PatchId,
+        configuration: &EvgPatchConfiguration,
+    ) -> Result<EvgPatch, EvgError> {
+        let url = format!("{}/configure", self.build_url("patches", patch_id.as_str()));
+        let response = self
+            .send(self.request(Method::POST, &url).json(configuration))
+            .await?;
+        parse_response(response).await
+    }
+
+    async fn list_projects(&self) -> Result<Vec<EvgProject>, EvgError> {
+        let url = format!("{}/rest/v2/projects", self.api_server_host);
+        self.collect_pages(self.get(&url)).await
     }
 
-    fn stream_versions(&self, project_id: &str) -> BoxedStream<EvgVersion> {
+    async fn get_project(&self, project_id: &ProjectId) -> Result<EvgProject, EvgError> {
+        let url = self.build_url("projects", project_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn get_project_variables(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<EvgProjectVariables, EvgError> {
         let url = format!(
-            "{}/versions?requester=gitter_request",
-            self.build_url("projects", project_id)
+            "{}/variables",
+            self.build_url("projects", project_id.as_str())
         );
-        let client = self.client.clone();
-
-        Box::pin(stream! {
-            let mut response = client.get(&url).send().await.unwrap();
-            loop {
-                let next_link = next_link(&response);
-                let result_batch: Vec<EvgVersion> = response.json().await.unwrap();
-                for version in result_batch {
-                    yield version;
-                }
+        let response = self.send(self.get(&url)).await?;
+        let variables: EvgProjectVariables = parse_response(response).await?;
+        Ok(variables.masked())
+    }
 
-                if let Some(next) = next_link {
-                    response = client.get(&next).send().await.unwrap();
-                } else {
-                    break;
-                }
+    async fn get_project_aliases(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<Vec<EvgProjectAlias>, EvgError> {
+        let url = self.build_url("alias", project_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn get_host(&self, host_id: &HostId) -> Result<EvgHost, EvgError> {
+        let url = self.build_url("hosts", host_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn get_host_events(&self, host_id: &HostId) -> Result<Vec<EvgHostEvent>, EvgError> {
+        let url = format!("{}/events", self.build_url("hosts", host_id.as_str()));
+        self.collect_pages(self.get(&url)).await
+    }
+
+    async fn get_commit_queue(&self, project_id: &ProjectId) -> Result<EvgCommitQueue, EvgError> {
+        let url = self.build_url("commit_queue", project_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    async fn enqueue_patch(&self, patch_id: &PatchId, force: bool) -> Result<usize, EvgError> {
+        let url = self.build_url("commit_queue", patch_id.as_str());
+        let request = self.request(Method::PUT, &url).query(&[("force", force)]);
+        let response = self.send(request).await?;
+        let position: EvgCommitQueuePosition = parse_response(response).await?;
+        Ok(position.position)
+    }
+
+    async fn dequeue_patch(
+        &self,
+        project_id: &ProjectId,
+        patch_id: &PatchId,
+    ) -> Result<(), EvgError> {
+        let url = format!(
+            "{}/{}",
+            self.build_url("commit_queue", project_id.as_str()),
+            patch_id
+        );
+        self.send_action(self.request(Method::DELETE, &url)).await
+    }
+
+    async fn list_distros(&self) -> Result<Vec<EvgDistro>, EvgError> {
+        let url = format!("{}/rest/v2/distros", self.api_server_host);
+        self.collect_pages(self.get(&url)).await
+    }
+
+    async fn get_distro(&self, distro_id: &DistroId) -> Result<EvgDistro, EvgError> {
+        let url = self.build_url("distros", distro_id.as_str());
+        let response = self.send(self.get(&url)).await?;
+        parse_response(response).await
+    }
+
+    fn stream_hosts(&self, filter: &EvgHostFilter) -> EvgStream<EvgHost> {
+        let evg_client = self.clone();
+        let filter = filter.clone();
+
+        Box::pin(try_stream! {
+            let mut url = format!("{}/rest/v2/hosts", evg_client.api_server_host);
+            let query = serde_urlencoded::to_string(&filter).map_err(|err| {
+                EvgError::config_with_source("could not encode the host filter", err)
+            })?;
+            if !query.is_empty() {
+                url = format!("{}?{}", url, query);
+            }
+            let mut hosts = evg_client.paginate(url);
+            while let Some(host) = hosts.next().await {
+                yield host?;
             }
         })
     }
 
-    fn stream_user_patches(&self, user_id: &str, limit: Option<usize>) -> BoxedStream<EvgPatch> {
+    fn stream_distro_queue(&self, distro_id: &DistroId) -> EvgStream<EvgTaskQueueItem> {
+        let url = format!("{}/queue", self.build_url("distros", distro_id.as_str()));
+        self.paginate(url)
+    }
+
+    fn stream_versions(&self, project_id: &ProjectId) -> EvgStream<EvgVersion> {
+        let url = format!(
+            "{}/versions?requester=gitter_request",
+            self.build_url("projects", project_id.as_str())
+        );
+        self.paginate(url)
+    }
+
+    fn stream_user_patches(&self, user_id: &str, limit: Option<usize>) -> EvgStream<EvgPatch> {
         let mut url = format!("{}/patches", self.build_url("users", user_id));
         if let Some(l) = limit {
             url = format!("{}?limit={}", url, l);
         }
-        let client = self.client.clone();
-
-        Box::pin(stream! {
-            let mut response = client.get(&url).send().await.unwrap();
-            loop {
-                let next_link = next_link(&response);
-                let result_batch: Vec<EvgPatch> = response.json().await.unwrap();
-                for patch in result_batch {
-                    yield patch;
-                }
-
-                if let Some(next) = next_link {
-                    response = client.get(&next).send().await.unwrap();
-                } else {
-                    break;
-                }
-            }
-        })
+        self.paginate(url)
     }
 
     fn stream_project_patches(
         &self,
-        project_id: &str,
+        project_id: &ProjectId,
         limit: Option<usize>,
-    ) -> BoxedStream<EvgPatch> {
-        let mut url = format!("{}/patches", self.build_url("projects", project_id));
+    ) -> EvgStream<EvgPatch> {
+        let mut url = format!(
+            "{}/patches",
+            self.build_url("projects", project_id.as_str())
+        );
         if let Some(l) = limit {
             url = format!("{}?limit={}", url, l);
         }
-        let client = self.client.clone();
-
-        Box::pin(stream! {
-            let mut response = client.get(&url).send().await.unwrap();
-            loop {
-                let next_link = next_link(&response);
-                let result_batch: Vec<EvgPatch> = response.json().await.unwrap();
-                for patch in result_batch {
-                    yield patch;
-                }
-
-                if let Some(next) = next_link {
-                    response = client.get(&next).send().await.unwrap();
-                } else {
-                    break;
-                }
-            }
-        })
+        self.paginate(url)
     }
 
-    fn stream_build_tasks(&self, build_id: &str, status: Option<&str>) -> BoxedStream<EvgTask> {
-        let mut url = format!("{}/tasks", self.build_url("builds", build_id));
+    fn stream_build_tasks(&self, build_id: &BuildId, status: Option<&str>) -> EvgStream<EvgTask> {
+        let mut url = format!("{}/tasks", self.build_url("builds", build_id.as_str()));
         if let Some(s) = status {
             url = format!("{}?status={}", url, s);
         }
-        let client = self.client.clone();
-
-        Box::pin(stream! {
-            let mut response = client.get(&url).send().await.unwrap();
-            loop {
-                let next_link = next_link(&response);
-                let result_batch: Vec<EvgTask> = response.json().await.unwrap();
-                for patch in result_batch {
-                    yield patch;
-                }
+        self.paginate(url)
+    }
 
-                if let Some(next) = next_link {
-                    response = client.get(&next).send().await.unwrap();
-                } else {
-                    break;
-                }
+    fn stream_log(&self, task: &EvgTask, log_name: &str) -> EvgStream<String> {
+        match task.logs.get(log_name) {
+            Some(log_url) => self.stream_lines(format!("{}&text=true", log_url)),
+            None => {
+                let err = EvgError::MissingLog {
+                    task_id: task.task_id.clone(),
+                    log_name: log_name.to_string(),
+                };
+                Box::pin(futures::stream::once(async { Err(err) }))
             }
-        })
+        }
     }
 
-    fn stream_log(&self, task: &EvgTask, log_name: &str) -> BoxedStream<String> {
-        let task_log = format!("{}&text=true", task.logs.get(log_name).unwrap());
-        let stream_future = self.client.get(&task_log).send();
-        Box::pin(stream! {
-            let mut stream = stream_future.await.unwrap().bytes_stream();
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(bytes) => {
-                        let lines = std::str::from_utf8(&bytes).unwrap().split('\n');
-                        for l in lines {
-                            yield l.to_string();
-                        }
-                    }
-                    _ => break,
-                }
+    fn stream_execution_log(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+        log_name: &str,
+    ) -> EvgStream<String> {
+        let client = self.clone();
+        let task_id = task_id.clone();
+        let log_name = log_name.to_string();
+        Box::pin(try_stream! {
+            let task = client.get_task_execution(&task_id, execution).await?;
+            let mut lines = client.stream_log(&task, &log_name);
+            while let Some(line) = lines.next().await {
+                yield line?;
             }
         })
     }
 
-    fn stream_test_log(&self, test: &EvgTest) -> BoxedStream<String> {
-        let stream_future = self.client.get(&test.logs.url_raw).send();
+    fn stream_test_log(&self, test: &EvgTest) -> EvgStream<String> {
+        self.stream_lines(test.logs.url_raw.clone())
+    }
+}
 
-        Box::pin(stream! {
-            let mut stream = stream_future.await.unwrap().bytes_stream();
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(bytes) => {
-                        let lines = std::str::from_utf8(&bytes).unwrap().split('\n');
-                        for l in lines {
-                            yield l.to_string();
-                        }
-                    }
-                    _ => break,
-                }
+/// Semaphore allowing `max_in_flight` requests at once, rejecting a limit of 0 since no request
+/// could ever start.
+pub(crate) fn in_flight_limit(max_in_flight: usize) -> Result<Arc<Semaphore>, EvgError> {
+    if max_in_flight == 0 {
+        return Err(EvgError::config("max_in_flight must be at least 1"));
+    }
+    Ok(Arc::new(Semaphore::new(max_in_flight)))
+}
+
+/// Check the status of the given response, converting failures into an `EvgError`.
+async fn check_response(response: Response) -> Result<Response, EvgError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let url = response.url().to_string();
+    if status == StatusCode::NOT_FOUND {
+        return Err(EvgError::NotFound { url });
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(EvgError::HttpStatus { url, status, body })
+}
+
+/// Check the status of the given response and deserialize its body.
+async fn parse_response<T: DeserializeOwned>(response: Response) -> Result<T, EvgError> {
+    let response = check_response(response).await?;
+    let url = response.url().to_string();
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| EvgError::deserialize(&url, &body, e))
+}
+
+/// Split a stream of byte chunks into lines, joining lines that span chunk boundaries.
+fn split_lines<S, B>(chunks: S) -> impl Stream<Item = Result<String, EvgError>>
+where
+    S: Stream<Item = Result<B, reqwest::Error>>,
+    B: AsRef<[u8]>,
+{
+    try_stream! {
+        futures::pin_mut!(chunks);
+        let mut buffer: Vec<u8> = vec![];
+        while let Some(chunk) = chunks.next().await {
+            buffer.extend_from_slice(chunk?.as_ref());
+            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                yield String::from_utf8_lossy(&line[..pos]).into_owned();
             }
-        })
+        }
+        if !buffer.is_empty() {
+            yield String::from_utf8_lossy(&buffer).into_owned();
+        }
     }
 }
 
 fn next_link(response: &Response) -> Option<String> {
     if let Some(header) = response.headers().get(LINK) {
-        let links = parse_link_header::parse(header.to_str().unwrap()).unwrap();
+        let links = parse_link_header::parse(header.to_str().ok()?).ok()?;
         let next_link = links.get(&Some("next".to_string()));
 
         return next_link.map(|l| l.uri.to_string());
@@ -332,6 +1037,12 @@ fn next_link(response: &Response) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::patch::EvgPatchVariantSelection;
+    use crate::models::project::MASKED_VALUE;
+    use crate::testing::fixtures::{
+        self, host_json, patch_json, project_json, task_json, test_json, version_json,
+    };
+    use crate::testing::StubServer;
     use http::response::Builder;
     use reqwest::Response;
 
@@ -373,4 +1084,513 @@ mod tests {
 
         assert_eq!(next_link, None);
     }
+
+    #[tokio::test]
+    async fn test_parse_response_should_return_not_found_on_404() {
+        let response = Response::from(Builder::new().status(404).body("").unwrap());
+
+        let result: Result<EvgTask, EvgError> = parse_response(response).await;
+
+        assert!(result.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_parse_response_should_include_status_and_body_on_failure() {
+        let response = Response::from(Builder::new().status(401).body("unauthorized").unwrap());
+
+        let result: Result<EvgTask, EvgError> = parse_response(response).await;
+
+        match result.unwrap_err() {
+            EvgError::HttpStatus { status, body, .. } => {
+                assert_eq!(status, StatusCode::UNAUTHORIZED);
+                assert_eq!(body, "unauthorized");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_response_should_include_snippet_on_bad_payload() {
+        let response = Response::from(Builder::new().status(200).body("not json").unwrap());
+
+        let result: Result<EvgTask, EvgError> = parse_response(response).await;
+
+        match result.unwrap_err() {
+            EvgError::Deserialize { snippet, .. } => assert_eq!(snippet, "not json"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_lines_should_join_lines_across_chunks() {
+        let chunks: Vec<Result<&[u8], reqwest::Error>> =
+            vec![Ok(b"first li"), Ok(b"ne\nsecond line\nthi"), Ok(b"rd")];
+
+        let lines: Vec<String> = split_lines(futures::stream::iter(chunks))
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(lines, vec!["first line", "second line", "third"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_task_should_fetch_task_from_server() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0",
+            &task_json("task_0", "build", "success"),
+        );
+        let client = server.client();
+
+        let task = client.get_task(&"task_0".into()).await.unwrap();
+        let missing = client.get_task(&"task_1".into()).await;
+
+        assert_eq!(task.task_id, "task_0");
+        assert!(missing.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_get_execution_tests_should_request_the_execution() {
+        let server = StubServer::start().await;
+        let tests = vec![test_json("task_0", "test_a", "fail")];
+        server.add_pages("/rest/v2/tasks/task_0/tests", &tests, 10);
+        let client = server.client();
+
+        let tests = client
+            .get_execution_tests(&"task_0".into(), 1)
+            .await
+            .unwrap();
+
+        let request = server.last_request("/rest/v2/tasks/task_0/tests").unwrap();
+        assert_eq!(tests.len(), 1);
+        assert_eq!(request.query_param("execution"), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_task_executions_should_fetch_every_execution() {
+        let server = StubServer::start().await;
+        let mut task = task_json("task_0", "build", "success");
+        task["execution"] = serde_json::json!(2);
+        server.add_json("/rest/v2/tasks/task_0", &task);
+        let client = server.client();
+
+        let executions: Vec<EvgTask> = client
+            .stream_task_executions(&"task_0".into())
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(executions.len(), 3);
+        assert_eq!(server.requests("/rest/v2/tasks/task_0"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_configure_patch_should_return_updated_patch() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/patches/patch_0/configure",
+            &patch_json("patch_0", "created"),
+        );
+        let client = server.client();
+        let configuration = EvgPatchConfiguration {
+            description: None,
+            variants: vec![EvgPatchVariantSelection {
+                variant: String::from("variant"),
+                tasks: vec![String::from("compile")],
+            }],
+        };
+
+        let patch = client
+            .configure_patch(&"patch_0".into(), &configuration)
+            .await
+            .unwrap();
+
+        assert_eq!(patch.patch_id, "patch_0");
+        let request = server
+            .last_request("/rest/v2/patches/patch_0/configure")
+            .unwrap();
+        assert_eq!(patch.variants_tasks.unwrap()[0].tasks, vec!["compile"]);
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(
+            request.json()["variants"],
+            serde_json::json!([{ "id": "variant", "tasks": ["compile"] }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abort_build_should_not_send_abort_on_dry_run() {
+        let server = StubServer::start().await;
+        let tasks = vec![
+            task_json("task_0", "build", "started"),
+            task_json("task_1", "build", "success"),
+        ];
+        server.add_pages("/rest/v2/builds/build/tasks", &tasks, 10);
+        server.add_json_str("/rest/v2/builds/build/abort", "{}");
+        let client = server.client();
+
+        let planned = client.abort_build(&"build".into(), true).await.unwrap();
+        let requests_after_dry_run = server.requests("/rest/v2/builds/build/abort");
+        let applied = client.abort_build(&"build".into(), false).await.unwrap();
+
+        assert!(planned.dry_run);
+        assert_eq!(planned.affected_tasks, vec!["task_0"]);
+        assert_eq!(requests_after_dry_run, 0);
+        assert_eq!(applied.affected_tasks, planned.affected_tasks);
+        assert_eq!(server.requests("/rest/v2/builds/build/abort"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_build_updates_should_patch_the_build() {
+        let server = StubServer::start().await;
+        let tasks = vec![task_json("task_0", "build", "undispatched")];
+        server.add_pages("/rest/v2/builds/build/tasks", &tasks, 10);
+        server.add_json_str("/rest/v2/builds/build", "{}");
+        let client = server.client();
+
+        client
+            .activate_build(&"build".into(), false, false)
+            .await
+            .unwrap();
+        client
+            .set_build_priority(&"build".into(), 75, false)
+            .await
+            .unwrap();
+
+        let requests = server.received("/rest/v2/builds/build");
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.method == Method::PATCH));
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({ "activated": false })
+        );
+        assert_eq!(requests[1].json(), serde_json::json!({ "priority": 75 }));
+    }
+
+    #[tokio::test]
+    async fn test_version_actions_should_abort_version_and_restart_failed_tasks() {
+        let server = StubServer::start().await;
+        let mut version = version_json("version", 1);
+        version["build_variants_status"] =
+            serde_json::json!([{ "build_variant": "variant", "build_id": "build" }]);
+        server.add_json("/rest/v2/versions/version", &version);
+        let tasks = vec![
+            task_json("task_0", "build", "failed"),
+            task_json("task_1", "build", "started"),
+        ];
+        server.add_pages("/rest/v2/builds/build/tasks", &tasks, 10);
+        server.add_json(
+            "/rest/v2/tasks/task_0/restart",
+            &task_json("task_0", "build", "undispatched"),
+        );
+        server.add_json_str("/rest/v2/versions/version/abort", "{}");
+        let client = server.client();
+
+        let restarted = client
+            .restart_version(&"version".into(), false)
+            .await
+            .unwrap();
+        let aborted = client
+            .abort_version(&"version".into(), false)
+            .await
+            .unwrap();
+
+        let restart = server
+            .last_request("/rest/v2/tasks/task_0/restart")
+            .unwrap();
+        let abort = server
+            .last_request("/rest/v2/versions/version/abort")
+            .unwrap();
+        assert_eq!(restarted.affected_tasks, vec!["task_0"]);
+        assert_eq!(restart.method, Method::POST);
+        assert_eq!(restart.json(), serde_json::json!({ "failed_only": false }));
+        assert_eq!(server.requests("/rest/v2/tasks/task_1/restart"), 0);
+        assert_eq!(aborted.affected_tasks, vec!["task_1"]);
+        assert_eq!(abort.method, Method::POST);
+    }
+
+    #[tokio::test]
+    async fn test_restart_build_should_report_tasks_that_failed_to_restart() {
+        let server = StubServer::start().await;
+        let tasks = vec![
+            task_json("task_0", "build", "failed"),
+            task_json("task_1", "build", "failed"),
+            task_json("task_2", "build", "success"),
+        ];
+        server.add_pages("/rest/v2/builds/build/tasks", &tasks, 10);
+        server.add_json(
+            "/rest/v2/tasks/task_0/restart",
+            &task_json("task_0", "build", "undispatched"),
+        );
+        server.add_status("/rest/v2/tasks/task_1/restart", 500, "boom");
+        let client = server.client();
+
+        let report = client.restart_build(&"build".into(), false).await.unwrap();
+
+        assert!(!report.is_complete());
+        assert_eq!(report.affected_tasks, vec!["task_0"]);
+        assert_eq!(report.failed_tasks, vec!["task_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_project_api_should_list_projects_and_mask_variables() {
+        let server = StubServer::start().await;
+        let projects = vec![project_json("mongo", true), project_json("tools", false)];
+        server.add_pages("/rest/v2/projects", &projects, 1);
+        server.add_json(
+            "/rest/v2/projects/mongo/variables",
+            &serde_json::json!({
+                "vars": { "token": "secret", "region": "us-east-1" },
+                "private_vars": { "token": true }
+            }),
+        );
+        let client = server.client();
+
+        let projects = client.list_projects().await.unwrap();
+        let variables = client.get_project_variables(&"mongo".into()).await.unwrap();
+
+        let enabled: Vec<&str> = projects
+            .iter()
+            .filter(|p| p.is_enabled())
+            .map(|p| p.identifier.as_str())
+            .collect();
+        assert_eq!(projects.len(), 2);
+        assert_eq!(enabled, vec!["mongo"]);
+        assert_eq!(variables.vars["token"], MASKED_VALUE);
+        assert_eq!(variables.vars["region"], "us-east-1");
+    }
+
+    #[tokio::test]
+    async fn test_get_task_host_should_follow_host_id() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/hosts/host",
+            &host_json("host", "rhel80", "running"),
+        );
+        let client = server.client();
+        let mut task = fixtures::task("task_0", "build", "failed");
+
+        let host = client.get_task_host(&task).await.unwrap().unwrap();
+        task.host_id = HostId::from("");
+        let undispatched = client.get_task_host(&task).await.unwrap();
+
+        assert_eq!(host.distro.distro_id, "rhel80");
+        assert!(host.status.is_running());
+        assert!(undispatched.is_none());
+        assert_eq!(server.requests("/rest/v2/hosts/host"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_distro_queue_should_yield_items_with_durations() {
+        let server = StubServer::start().await;
+        let items: Vec<serde_json::Value> = (0..3)
+            .map(|i| {
+                serde_json::json!({
+                    "id": format!("task_{}", i),
+                    "display_name": "compile",
+                    "build_variant": "variant",
+                    "revision": "abc123",
+                    "project": "project",
+                    "version": "version",
+                    "exp_dur": 60_000_000_000u64
+                })
+            })
+            .collect();
+        server.add_pages("/rest/v2/distros/rhel80/queue", &items, 2);
+        let client = server.client();
+
+        let queue: Vec<EvgTaskQueueItem> = client
+            .stream_distro_queue(&"rhel80".into())
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        let ahead_of_last: std::time::Duration = queue
+            .iter()
+            .take_while(|item| item.id != "task_2")
+            .map(|item| item.expected_duration())
+            .sum();
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(ahead_of_last, std::time::Duration::from_secs(120));
+    }
+
+    #[tokio::test]
+    async fn test_stream_versions_should_follow_pagination() {
+        let server = StubServer::start().await;
+        let versions: Vec<serde_json::Value> = (0..5)
+            .map(|i| version_json(&format!("v{}", i), i))
+            .collect();
+        server.add_pages("/rest/v2/projects/project/versions", &versions, 2);
+        let client = server.client();
+
+        let version_ids: Vec<VersionId> = client
+            .stream_versions(&"project".into())
+            .map(|v| v.unwrap().version_id)
+            .collect()
+            .await;
+
+        let requesters: Vec<Option<String>> = server
+            .received("/rest/v2/projects/project/versions")
+            .iter()
+            .map(|r| r.query_param("requester").map(String::from))
+            .collect();
+        assert_eq!(version_ids, vec!["v0", "v1", "v2", "v3", "v4"]);
+        assert_eq!(requesters, vec![Some(String::from("gitter_request")); 3]);
+    }
+
+    #[tokio::test]
+    async fn test_get_task_should_retry_throttled_requests() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0",
+            &task_json("task_0", "build", "success"),
+        );
+        server.throttle("/rest/v2/tasks/task_0", 2, Some(0));
+        let client = server.client();
+
+        let task = client.get_task(&"task_0".into()).await.unwrap();
+
+        assert_eq!(task.task_id, "task_0");
+        assert_eq!(server.requests("/rest/v2/tasks/task_0"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_restart_task_should_post_failed_only() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0/restart",
+            &task_json("task_0", "build", "undispatched"),
+        );
+        let client = server.client();
+
+        client.restart_task(&"task_0".into(), true).await.unwrap();
+
+        let request = server
+            .last_request("/rest/v2/tasks/task_0/restart")
+            .unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.json(), serde_json::json!({ "failed_only": true }));
+    }
+
+    #[tokio::test]
+    async fn test_abort_task_should_post_to_abort() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0/abort",
+            &task_json("task_0", "build", "aborted"),
+        );
+        let client = server.client();
+
+        client.abort_task(&"task_0".into()).await.unwrap();
+
+        let request = server.last_request("/rest/v2/tasks/task_0/abort").unwrap();
+        assert_eq!(request.method, Method::POST);
+    }
+
+    #[tokio::test]
+    async fn test_task_updates_should_patch_the_task() {
+        let server = StubServer::start().await;
+        server.add_json(
+            "/rest/v2/tasks/task_0",
+            &task_json("task_0", "build", "undispatched"),
+        );
+        let client = server.client();
+
+        client
+            .set_task_activated(&"task_0".into(), false)
+            .await
+            .unwrap();
+        client
+            .set_task_priority(&"task_0".into(), 50)
+            .await
+            .unwrap();
+
+        let requests = server.received("/rest/v2/tasks/task_0");
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.method == Method::PATCH));
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({ "activated": false })
+        );
+        assert_eq!(requests[1].json(), serde_json::json!({ "priority": 50 }));
+    }
+
+    #[tokio::test]
+    async fn test_restart_task_should_not_resend_after_server_error() {
+        let server = StubServer::start().await;
+        server.add_status("/rest/v2/tasks/task_0/restart", 502, "bad gateway");
+        let client = server.client();
+
+        let result = client.restart_task(&"task_0".into(), false).await;
+
+        assert_eq!(result.unwrap_err().status(), Some(StatusCode::BAD_GATEWAY));
+        assert_eq!(server.requests("/rest/v2/tasks/task_0/restart"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_patch_mutations_should_not_resend_after_server_error() {
+        let server = StubServer::start().await;
+        for action in &["abort", "restart", "configure"] {
+            server.add_status(
+                &format!("/rest/v2/patches/patch_0/{}", action),
+                504,
+                "timeout",
+            );
+        }
+        let client = server.client();
+        let patch_id = PatchId::from("patch_0");
+        let configuration = EvgPatchConfiguration {
+            description: None,
+            variants: vec![],
+        };
+
+        assert!(client.abort_patch(&patch_id).await.is_err());
+        assert!(client.restart_patch(&patch_id).await.is_err());
+        assert!(client
+            .configure_patch(&patch_id, &configuration)
+            .await
+            .is_err());
+
+        for action in &["abort", "restart", "configure"] {
+            let path = format!("/rest/v2/patches/patch_0/{}", action);
+            assert_eq!(server.requests(&path), 1, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commit_queue_mutations_should_not_resend_after_server_error() {
+        let server = StubServer::start().await;
+        server.add_status("/rest/v2/commit_queue/patch_0", 502, "bad gateway");
+        server.add_status("/rest/v2/commit_queue/project/patch_0", 502, "bad gateway");
+        let client = server.client();
+        let patch_id = PatchId::from("patch_0");
+
+        assert!(client.enqueue_patch(&patch_id, true).await.is_err());
+        assert!(client
+            .dequeue_patch(&"project".into(), &patch_id)
+            .await
+            .is_err());
+
+        let enqueue = server.received("/rest/v2/commit_queue/patch_0");
+        assert_eq!(enqueue.len(), 1);
+        assert_eq!(enqueue[0].method, Method::PUT);
+        assert_eq!(enqueue[0].query_param("force"), Some("true"));
+        assert_eq!(server.requests("/rest/v2/commit_queue/project/patch_0"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_versions_should_end_after_error() {
+        let server = StubServer::start().await;
+        server.add_status("/rest/v2/projects/project/versions", 500, "boom");
+        let client = server.client().with_retry_policy(RetryPolicy::disabled());
+
+        let results: Vec<Result<EvgVersion, EvgError>> =
+            client.stream_versions(&"project".into()).collect().await;
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].as_ref().unwrap_err().status(),
+            Some(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+    }
 }
@@ -0,0 +1,110 @@
+use crate::EvgError;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting how many requests can be started per second.
+///
+/// Clones share the same bucket, so a limiter can be handed to many concurrent tasks.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a token if one is available, otherwise report how long until one will be.
+    fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `requests_per_second` requests on average, with bursts of up
+    /// to `burst` requests. A `burst` of 0 is treated as 1.
+    ///
+    /// Returns a configuration error if `requests_per_second` is not a positive number.
+    pub fn new(requests_per_second: f64, burst: u32) -> Result<Self, EvgError> {
+        if requests_per_second.is_nan() || requests_per_second <= 0.0 {
+            return Err(EvgError::config("requests_per_second must be positive"));
+        }
+        let capacity = f64::from(burst.max(1));
+        Ok(Self {
+            bucket: Arc::new(Mutex::new(TokenBucket {
+                capacity,
+                tokens: capacity,
+                refill_per_sec: requests_per_second,
+                last_refill: Instant::now(),
+            })),
+        })
+    }
+
+    /// Wait until a request is allowed to start.
+    pub async fn acquire(&self) {
+        loop {
+            let result = self
+                .bucket
+                .lock()
+                .expect("rate limiter lock poisoned")
+                .try_take(Instant::now());
+            match result {
+                Ok(()) => return,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_should_allow_burst_then_wait_for_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket {
+            capacity: 2.0,
+            tokens: 2.0,
+            refill_per_sec: 10.0,
+            last_refill: start,
+        };
+
+        assert!(bucket.try_take(start).is_ok());
+        assert!(bucket.try_take(start).is_ok());
+        assert_eq!(bucket.try_take(start), Err(Duration::from_millis(100)));
+        assert!(bucket.try_take(start + Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn test_new_should_reject_non_positive_rate() {
+        assert!(RateLimiter::new(10.0, 1).is_ok());
+        assert!(matches!(
+            RateLimiter::new(0.0, 1),
+            Err(EvgError::Config { .. })
+        ));
+        assert!(matches!(
+            RateLimiter::new(f64::NAN, 1),
+            Err(EvgError::Config { .. })
+        ));
+    }
+}
@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Deserialize, Clone)]
+use super::ids::{BuildId, ProjectId, VersionId};
+use super::status::BuildStatus;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct BuildStatusCounts {
     pub succeeded: u32,
     pub failed: u32,
@@ -50,23 +53,26 @@ impl BuildStatusCounts {
     }
 
     pub fn percent_complete(&self) -> f64 {
-        self.finished_task_count() as f64 / self.total_task_count() as f64
+        match self.total_task_count() {
+            0 => 0.0,
+            total => self.finished_task_count() as f64 / total as f64,
+        }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgBuild {
     #[serde(alias = "_id")]
-    pub id: String,
-    pub project_id: String,
+    pub id: BuildId,
+    pub project_id: ProjectId,
     pub create_time: Option<DateTime<Utc>>,
     pub start_time: Option<DateTime<Utc>>,
     pub finish_time: Option<DateTime<Utc>>,
-    pub version: String,
+    pub version: VersionId,
     pub branch: Option<String>,
     pub git_hash: String,
     pub build_variant: String,
-    pub status: String,
+    pub status: BuildStatus,
     pub activated: bool,
     pub activated_by: String,
     pub activated_time: Option<DateTime<Utc>>,
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ids::{PatchId, ProjectId, VersionId};
+use super::patch::EvgPatch;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgCommitQueueItem {
+    /// Id of the queued patch, or the pull request number for GitHub merges.
+    pub issue: String,
+    pub version: Option<VersionId>,
+    #[serde(rename = "enqueueTime")]
+    pub enqueue_time: Option<DateTime<Utc>>,
+    pub source: Option<String>,
+    pub patch: Option<EvgPatch>,
+}
+
+impl EvgCommitQueueItem {
+    /// Id of the queued patch.
+    pub fn patch_id(&self) -> PatchId {
+        PatchId::from(self.issue.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgCommitQueue {
+    #[serde(rename = "queue_id")]
+    pub project_id: ProjectId,
+    pub queue: Option<Vec<EvgCommitQueueItem>>,
+}
+
+impl EvgCommitQueue {
+    /// Queued items, starting with the one being merged.
+    pub fn items(&self) -> &[EvgCommitQueueItem] {
+        self.queue.as_deref().unwrap_or_default()
+    }
+
+    /// Zero-based position of the given patch in the queue.
+    pub fn position(&self, patch_id: &PatchId) -> Option<usize> {
+        self.items()
+            .iter()
+            .position(|i| i.issue == patch_id.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgCommitQueuePosition {
+    pub position: usize,
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::ids::{BuildId, DistroId, ProjectId, TaskId, VersionId};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgDistro {
+    pub name: DistroId,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub arch: String,
+    pub provider: String,
+    pub work_dir: Option<String>,
+    pub user: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgTaskQueueItem {
+    pub id: TaskId,
+    pub display_name: String,
+    pub build_variant: String,
+    pub revision: String,
+    pub requester: Option<String>,
+    pub project: ProjectId,
+    pub version: VersionId,
+    pub build_id: Option<BuildId>,
+    pub priority: Option<i64>,
+    /// Expected run time of the task in nanoseconds.
+    #[serde(rename = "exp_dur", default)]
+    pub expected_duration_ns: u64,
+}
+
+impl EvgTaskQueueItem {
+    /// How long the task is expected to run once dispatched.
+    pub fn expected_duration(&self) -> Duration {
+        Duration::from_nanos(self.expected_duration_ns)
+    }
+}
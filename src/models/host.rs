@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ids::{BuildId, DistroId, HostId, TaskId, VersionId};
+use super::status::HostStatus;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgHostDistro {
+    pub distro_id: DistroId,
+    pub provider: String,
+    pub image_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgHostRunningTask {
+    pub task_id: Option<TaskId>,
+    pub name: Option<String>,
+    pub dispatch_time: Option<DateTime<Utc>>,
+    pub version_id: Option<VersionId>,
+    pub build_id: Option<BuildId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgHost {
+    pub host_id: HostId,
+    pub host_url: String,
+    pub distro: EvgHostDistro,
+    pub provisioned: bool,
+    pub started_by: String,
+    pub host_type: Option<String>,
+    pub user: Option<String>,
+    pub status: HostStatus,
+    pub running_task: Option<EvgHostRunningTask>,
+    #[serde(default)]
+    pub user_host: bool,
+    pub no_expiration: Option<bool>,
+    pub last_communication: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgHostEvent {
+    pub timestamp: DateTime<Utc>,
+    #[serde(alias = "eventtype")]
+    pub event_type: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+/// Filters applied when listing hosts.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct EvgHostFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<HostStatus>,
+    #[serde(rename = "distro", skip_serializing_if = "Option::is_none")]
+    pub distro_id: Option<DistroId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_filter_should_encode_set_fields_only() {
+        let filter = EvgHostFilter {
+            status: Some(HostStatus::ProvisionFailed),
+            distro_id: Some(DistroId::from("rhel80")),
+        };
+
+        assert_eq!(
+            serde_urlencoded::to_string(&filter).unwrap(),
+            "status=provision+failed&distro=rhel80"
+        );
+        assert_eq!(
+            serde_urlencoded::to_string(EvgHostFilter::default()).unwrap(),
+            ""
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Define a newtype wrapping the string id of an evergreen object.
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            /// The id as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// Convert the id back into a plain string.
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                $name(id.to_string())
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> Self {
+                $name(id.clone())
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+id_type! {
+    /// Id of an evergreen task.
+    TaskId
+}
+
+id_type! {
+    /// Id of an evergreen build.
+    BuildId
+}
+
+id_type! {
+    /// Id of an evergreen version.
+    VersionId
+}
+
+id_type! {
+    /// Id of an evergreen patch.
+    PatchId
+}
+
+id_type! {
+    /// Id or identifier of an evergreen project.
+    ProjectId
+}
+
+id_type! {
+    /// Id of an evergreen host.
+    HostId
+}
+
+id_type! {
+    /// Name of an evergreen distro.
+    DistroId
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_should_serialize_as_plain_string() {
+        let id: TaskId = serde_json::from_str("\"task_0\"").unwrap();
+
+        assert_eq!(id, TaskId::from("task_0"));
+        assert_eq!(id.to_string(), "task_0");
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"task_0\"");
+    }
+}
@@ -1,6 +1,12 @@
 pub mod build;
+pub mod commit_queue;
+pub mod distro;
+pub mod host;
+pub mod ids;
 pub mod patch;
+pub mod project;
 pub mod stats;
+pub mod status;
 pub mod task;
 pub mod test;
 pub mod version;
@@ -1,19 +1,51 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use super::ids::{PatchId, ProjectId, VersionId};
+use super::status::PatchStatus;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgVariantTasks {
+    pub name: String,
+    pub tasks: Vec<String>,
+    #[serde(default)]
+    pub display_tasks: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgPatch {
-    pub patch_id: String,
+    pub patch_id: PatchId,
     pub description: String,
-    pub project_id: String,
+    pub project_id: ProjectId,
     pub project_identifier: String,
     pub branch: String,
     pub git_hash: String,
     pub patch_number: u64,
     pub author: String,
-    pub version: String,
-    pub status: String,
+    pub version: VersionId,
+    pub status: PatchStatus,
     pub create_time: DateTime<Utc>,
     pub start_time: Option<DateTime<Utc>>,
     pub finish_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub activated: bool,
+    pub alias: Option<String>,
+    pub variants_tasks: Option<Vec<EvgVariantTasks>>,
+    pub commit_queue_position: Option<usize>,
+}
+
+/// Tasks to schedule for one build variant when configuring a patch.
+#[derive(Debug, Serialize, Clone)]
+pub struct EvgPatchVariantSelection {
+    #[serde(rename = "id")]
+    pub variant: String,
+    pub tasks: Vec<String>,
+}
+
+/// Body of a request to select the variants and tasks of a patch and finalize it.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct EvgPatchConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub variants: Vec<EvgPatchVariantSelection>,
 }
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::ids::ProjectId;
+
+/// Value reported in place of private project variables.
+pub const MASKED_VALUE: &str = "{REDACTED}";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgProject {
+    pub id: ProjectId,
+    pub identifier: String,
+    #[serde(default)]
+    pub display_name: String,
+    pub owner_name: String,
+    pub repo_name: String,
+    pub branch_name: String,
+    pub enabled: Option<bool>,
+    pub private: Option<bool>,
+    pub remote_path: Option<String>,
+    pub batch_time: Option<i64>,
+    pub deactivate_previous: Option<bool>,
+    pub patching_disabled: Option<bool>,
+    pub dispatching_disabled: Option<bool>,
+    pub admins: Option<Vec<String>>,
+}
+
+impl EvgProject {
+    /// Check if the project is enabled, treating a missing flag as disabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EvgProjectVariables {
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    #[serde(default)]
+    pub private_vars: HashMap<String, bool>,
+}
+
+impl EvgProjectVariables {
+    /// Replace the values of private variables with `MASKED_VALUE`.
+    pub fn masked(mut self) -> Self {
+        for (name, value) in self.vars.iter_mut() {
+            if self.private_vars.get(name).copied().unwrap_or(false) {
+                *value = MASKED_VALUE.to_string();
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvgProjectAlias {
+    pub alias: String,
+    #[serde(default)]
+    pub variant: String,
+    #[serde(default)]
+    pub task: String,
+    #[serde(default)]
+    pub variant_tags: Vec<String>,
+    #[serde(default)]
+    pub task_tags: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masked_should_hide_private_values() {
+        let mut variables = EvgProjectVariables::default();
+        variables
+            .vars
+            .insert(String::from("token"), String::from("secret"));
+        variables
+            .vars
+            .insert(String::from("region"), String::from("us-east-1"));
+        variables.private_vars.insert(String::from("token"), true);
+
+        let masked = variables.masked();
+
+        assert_eq!(masked.vars["token"], MASKED_VALUE);
+        assert_eq!(masked.vars["region"], "us-east-1");
+    }
+}
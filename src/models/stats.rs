@@ -23,7 +23,7 @@ impl Default for EvgTestStatsRequest {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgTestStats {
     pub test_file: String,
     pub task_name: String,
@@ -56,7 +56,7 @@ impl Default for EvgTaskStatsRequest {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgTaskStats {
     pub task_name: String,
     pub variant: String,
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Define a status enum serialized as its evergreen string, with an `Unknown` fallback for
+/// values this crate does not know about yet.
+macro_rules! status_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A status not known to this crate.
+            Unknown(String),
+        }
+
+        impl $name {
+            /// The status as reported by evergreen.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    _ => $name::Unknown(value.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                $name::from(value.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(status: $name) -> Self {
+                status.as_str().to_string()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.as_str() == *other
+            }
+        }
+    };
+}
+
+status_enum! {
+    /// Status of an evergreen task.
+    TaskStatus {
+        Undispatched => "undispatched",
+        Inactive => "inactive",
+        Unscheduled => "unscheduled",
+        WillRun => "will-run",
+        Pending => "pending",
+        Blocked => "blocked",
+        Dispatched => "dispatched",
+        Started => "started",
+        Success => "success",
+        Failed => "failed",
+        SetupFailed => "setup-failed",
+        SystemFailed => "system-failed",
+        SystemTimedOut => "system-timed-out",
+        SystemUnresponsive => "system-unresponsive",
+        TestTimedOut => "test-timed-out",
+        TaskTimedOut => "task-timed-out",
+        KnownIssue => "known-issue",
+        Aborted => "aborted",
+    }
+}
+
+impl TaskStatus {
+    /// Check if the task has finished running.
+    pub fn is_finished(&self) -> bool {
+        self.is_success() || self.is_failure() || *self == TaskStatus::Aborted
+    }
+
+    /// Check if the task finished successfully.
+    pub fn is_success(&self) -> bool {
+        *self == TaskStatus::Success
+    }
+
+    /// Check if the task finished unsuccessfully, for any reason.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Failed
+                | TaskStatus::SetupFailed
+                | TaskStatus::SystemFailed
+                | TaskStatus::SystemTimedOut
+                | TaskStatus::SystemUnresponsive
+                | TaskStatus::TestTimedOut
+                | TaskStatus::TaskTimedOut
+                | TaskStatus::KnownIssue
+        )
+    }
+
+    /// Check if the task failed because of a problem with the system it ran on.
+    pub fn is_system_failure(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::SystemFailed | TaskStatus::SystemTimedOut | TaskStatus::SystemUnresponsive
+        )
+    }
+
+    /// Check if the task is scheduled or running but has not finished.
+    pub fn is_in_progress(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Undispatched
+                | TaskStatus::WillRun
+                | TaskStatus::Pending
+                | TaskStatus::Dispatched
+                | TaskStatus::Started
+        )
+    }
+}
+
+status_enum! {
+    /// Status of an evergreen build. Versions share the same statuses.
+    BuildStatus {
+        Created => "created",
+        Started => "started",
+        Success => "success",
+        Failed => "failed",
+        Aborted => "aborted",
+    }
+}
+
+/// Status of an evergreen version.
+pub type VersionStatus = BuildStatus;
+
+impl BuildStatus {
+    /// Check if all tasks have finished running.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            BuildStatus::Success | BuildStatus::Failed | BuildStatus::Aborted
+        )
+    }
+
+    /// Check if all tasks finished successfully.
+    pub fn is_success(&self) -> bool {
+        *self == BuildStatus::Success
+    }
+
+    /// Check if the build finished with failures.
+    pub fn is_failure(&self) -> bool {
+        *self == BuildStatus::Failed
+    }
+}
+
+status_enum! {
+    /// Status of an evergreen patch.
+    PatchStatus {
+        Created => "created",
+        Started => "started",
+        Succeeded => "succeeded",
+        Failed => "failed",
+    }
+}
+
+impl PatchStatus {
+    /// Check if all tasks of the patch have finished running.
+    pub fn is_finished(&self) -> bool {
+        self.is_success() || self.is_failure()
+    }
+
+    /// Check if all tasks of the patch finished successfully.
+    pub fn is_success(&self) -> bool {
+        *self == PatchStatus::Succeeded
+    }
+
+    /// Check if the patch finished with failures.
+    pub fn is_failure(&self) -> bool {
+        *self == PatchStatus::Failed
+    }
+}
+
+status_enum! {
+    /// Status of a test result.
+    TestStatus {
+        Pass => "pass",
+        Fail => "fail",
+        SilentFail => "silentfail",
+        Skip => "skip",
+        Timeout => "timeout",
+    }
+}
+
+impl TestStatus {
+    /// Check if the test passed.
+    pub fn is_success(&self) -> bool {
+        *self == TestStatus::Pass
+    }
+
+    /// Check if the test failed or timed out.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            TestStatus::Fail | TestStatus::SilentFail | TestStatus::Timeout
+        )
+    }
+}
+
+status_enum! {
+    /// Status of an evergreen host.
+    HostStatus {
+        Initializing => "initializing",
+        Building => "building",
+        BuildingFailed => "building-failed",
+        Starting => "starting",
+        Provisioning => "provisioning",
+        ProvisionFailed => "provision failed",
+        Running => "running",
+        Stopping => "stopping",
+        Stopped => "stopped",
+        Quarantined => "quarantined",
+        Decommissioned => "decommissioned",
+        Terminated => "terminated",
+    }
+}
+
+impl HostStatus {
+    /// Check if the host is up and able to run tasks.
+    pub fn is_running(&self) -> bool {
+        *self == HostStatus::Running
+    }
+
+    /// Check if the host is gone or going away.
+    pub fn is_terminated(&self) -> bool {
+        matches!(
+            self,
+            HostStatus::Decommissioned | HostStatus::Terminated | HostStatus::BuildingFailed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_should_round_trip_through_json() {
+        let status: TaskStatus = serde_json::from_str("\"setup-failed\"").unwrap();
+
+        assert_eq!(status, TaskStatus::SetupFailed);
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"setup-failed\"");
+        assert!(status.is_failure());
+        assert!(status.is_finished());
+    }
+
+    #[test]
+    fn test_patch_status_should_round_trip_succeeded() {
+        let status: PatchStatus = serde_json::from_str("\"succeeded\"").unwrap();
+
+        assert_eq!(status, PatchStatus::Succeeded);
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"succeeded\"");
+        assert!(status.is_success());
+        assert!(status.is_finished());
+    }
+
+    #[test]
+    fn test_status_should_keep_unknown_values() {
+        let status: BuildStatus = serde_json::from_str("\"paused\"").unwrap();
+
+        assert_eq!(status, BuildStatus::Unknown(String::from("paused")));
+        assert_eq!(status, "paused");
+        assert!(!status.is_finished());
+    }
+}
@@ -1,8 +1,14 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Clone)]
+use super::ids::{BuildId, DistroId, HostId, ProjectId, TaskId, VersionId};
+use super::status::TaskStatus;
+
+/// Required status meaning any finished status satisfies a dependency.
+const ANY_FINISHED_STATUS: &str = "*";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgTaskArtifact {
     pub name: String,
     pub url: String,
@@ -10,51 +16,90 @@ pub struct EvgTaskArtifact {
     pub ignore_for_fetch: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgTaskStatusDetails {
-    pub status: String,
+    pub status: TaskStatus,
     #[serde(alias = "type")]
     pub status_type: String,
     pub desc: String,
     pub timed_out: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// A task dependency, given either as a plain task id or with the status it requires.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EvgTaskDependency {
+    Id(TaskId),
+    WithStatus {
+        #[serde(rename = "id")]
+        task_id: TaskId,
+        status: String,
+    },
+}
+
+impl EvgTaskDependency {
+    /// Id of the task depended on.
+    pub fn task_id(&self) -> &TaskId {
+        match self {
+            EvgTaskDependency::Id(task_id) => task_id,
+            EvgTaskDependency::WithStatus { task_id, .. } => task_id,
+        }
+    }
+
+    /// Status the dependency must finish with, if other than success.
+    pub fn required_status(&self) -> Option<&str> {
+        match self {
+            EvgTaskDependency::WithStatus { status, .. } if !status.is_empty() => Some(status),
+            _ => None,
+        }
+    }
+
+    /// Check if a dependency finishing with the given status satisfies this requirement.
+    pub fn is_satisfied_by(&self, status: &TaskStatus) -> bool {
+        match self.required_status() {
+            None => status.is_success(),
+            Some(ANY_FINISHED_STATUS) => status.is_finished(),
+            Some(required) => *status == required || (required == "failed" && status.is_failure()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgTask {
     pub activated: bool,
     pub activated_by: String,
     pub artifacts: Option<Vec<EvgTaskArtifact>>,
-    pub build_id: String,
+    pub build_id: BuildId,
     pub build_variant: String,
     pub create_time: DateTime<Utc>,
-    // depends_on: Optional[List[Union[str, DisplayTaskDependency]]]
+    pub depends_on: Option<Vec<EvgTaskDependency>>,
     pub dispatch_time: Option<DateTime<Utc>>,
     pub display_name: String,
     pub display_only: bool,
-    pub distro_id: String,
+    pub distro_id: DistroId,
     pub est_wait_to_start_ms: u32,
     pub execution: u32,
-    pub execution_tasks: Option<Vec<String>>,
+    pub execution_tasks: Option<Vec<TaskId>>,
     pub expected_duration_ms: u64,
     pub finish_time: Option<DateTime<Utc>>,
     pub generate_task: bool,
     pub generated_by: String,
-    pub host_id: String,
+    pub host_id: HostId,
     pub ingest_time: Option<DateTime<Utc>>,
     pub logs: HashMap<String, String>,
     pub mainline: Option<bool>,
     pub order: u64,
-    pub project_id: String,
-    pub priority: u32,
+    pub project_id: ProjectId,
+    pub priority: i64,
     pub restarts: Option<u32>,
     pub revision: String,
     pub scheduled_time: Option<DateTime<Utc>>,
     pub start_time: Option<DateTime<Utc>>,
-    pub status: String,
+    pub status: TaskStatus,
     pub status_details: EvgTaskStatusDetails,
     pub task_group: Option<String>,
     pub task_group_max_hosts: Option<u16>,
-    pub task_id: String,
+    pub task_id: TaskId,
     pub time_taken_ms: u64,
-    pub version_id: String,
+    pub version_id: VersionId,
 }
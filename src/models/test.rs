@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use super::ids::TaskId;
+use super::status::TestStatus;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TestLog {
     pub url: String,
     pub line_num: u32,
@@ -11,10 +14,12 @@ pub struct TestLog {
     pub url_html_display: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgTest {
-    pub task_id: String,
-    pub status: String,
+    pub task_id: TaskId,
+    #[serde(default)]
+    pub execution: u32,
+    pub status: TestStatus,
     pub test_file: String,
     pub exit_code: u16,
     pub start_time: DateTime<Utc>,
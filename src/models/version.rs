@@ -1,25 +1,28 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use super::ids::{BuildId, ProjectId, VersionId};
+use super::status::VersionStatus;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BuildVariantStatus {
     pub build_variant: String,
-    pub build_id: String,
+    pub build_id: BuildId,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EvgVersion {
-    pub version_id: String,
+    pub version_id: VersionId,
     pub create_time: DateTime<Utc>,
     pub start_time: Option<DateTime<Utc>>,
     pub finish_time: Option<DateTime<Utc>>,
     pub revision: String,
     pub order: u64,
-    pub project: String,
+    pub project: ProjectId,
     pub author: String,
     pub author_email: String,
     pub message: String,
-    pub status: String,
+    pub status: VersionStatus,
     pub repo: String,
     pub branch: String,
     pub errors: Option<Vec<String>>,
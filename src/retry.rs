@@ -0,0 +1,225 @@
+use crate::EvgError;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use std::time::Duration;
+
+/// Policy describing when and how failed requests should be retried.
+///
+/// Requests are only retried for failures up to the arrival of the response headers. A transport
+/// error while reading the response body, such as a connection reset after the headers, is
+/// returned to the caller without a retry, even for GET requests, since the body may already be
+/// partly consumed, as with streamed logs.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts to make for a request, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every subsequent retry.
+    pub base_delay: Duration,
+    /// Upper bound on the delay between attempts.
+    pub max_delay: Duration,
+    /// Fraction of each delay (between 0.0 and 1.0) that is randomized.
+    pub jitter: f64,
+    /// Response status codes that should be retried.
+    pub retry_statuses: Vec<StatusCode>,
+    /// Retry requests that timed out.
+    pub retry_timeouts: bool,
+    /// Retry requests that failed to connect.
+    pub retry_connect_errors: bool,
+    /// Wait for the duration given in a `Retry-After` response header when present.
+    pub honor_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.5,
+            retry_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_timeouts: true,
+            retry_connect_errors: true,
+            honor_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Determine how long to wait before retrying the given response, if it should be retried.
+    pub(crate) fn retry_response(&self, attempt: u32, response: &Response) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.retry_statuses.contains(&response.status()) {
+            return None;
+        }
+
+        if self.honor_retry_after {
+            if let Some(delay) = retry_after(response) {
+                return Some(delay.min(self.max_delay));
+            }
+        }
+        Some(self.backoff(attempt))
+    }
+
+    /// Determine how long to wait before resending a request that is not safe to replay, if the
+    /// server rejected it without processing it.
+    ///
+    /// Such requests are only resent after a `429 Too Many Requests` or `503 Service
+    /// Unavailable` response carrying a `Retry-After` header.
+    pub(crate) fn retry_rejected(&self, attempt: u32, response: &Response) -> Option<Duration> {
+        let status = response.status();
+        let rejected =
+            status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE;
+        if attempt >= self.max_attempts
+            || !rejected
+            || !self.retry_statuses.contains(&status)
+            || !self.honor_retry_after
+        {
+            return None;
+        }
+        retry_after(response).map(|delay| delay.min(self.max_delay))
+    }
+
+    /// Determine how long to wait before retrying the given error, if it should be retried.
+    ///
+    /// Only errors raised while sending the request reach this; errors reading the response body
+    /// are never retried.
+    pub(crate) fn retry_error(&self, attempt: u32, err: &EvgError) -> Option<Duration> {
+        let err = match err {
+            EvgError::Transport(err) if attempt < self.max_attempts => err,
+            _ => return None,
+        };
+
+        if (err.is_timeout() && self.retry_timeouts)
+            || (err.is_connect() && self.retry_connect_errors)
+        {
+            Some(self.backoff(attempt))
+        } else {
+            None
+        }
+    }
+
+    /// Calculate the delay to use after the given (1-based) attempt failed.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        let reduction = rand::thread_rng().gen_range(0.0..=jitter);
+        delay.mul_f64(1.0 - reduction)
+    }
+}
+
+/// Read the delay requested by the server from the `Retry-After` header.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&Utc) - Utc::now();
+    Some(delay.to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::response::Builder;
+
+    fn policy_without_jitter() -> RetryPolicy {
+        RetryPolicy {
+            jitter: 0.0,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff_should_double_until_max_delay() {
+        let policy = policy_without_jitter();
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff(40), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_retry_response_should_honor_retry_after() {
+        let policy = RetryPolicy {
+            max_delay: Duration::from_secs(60),
+            ..policy_without_jitter()
+        };
+        let response = Response::from(
+            Builder::new()
+                .status(429)
+                .header(RETRY_AFTER, "7")
+                .body("")
+                .unwrap(),
+        );
+
+        assert_eq!(
+            policy.retry_response(1, &response),
+            Some(Duration::from_secs(7))
+        );
+    }
+
+    #[test]
+    fn test_retry_response_should_not_retry_other_statuses_or_last_attempt() {
+        let policy = policy_without_jitter();
+        let not_found = Response::from(Builder::new().status(404).body("").unwrap());
+        let unavailable = Response::from(Builder::new().status(503).body("").unwrap());
+
+        assert_eq!(policy.retry_response(1, &not_found), None);
+        assert_eq!(
+            policy.retry_response(policy.max_attempts, &unavailable),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retry_rejected_should_require_retry_after() {
+        let policy = policy_without_jitter();
+        let throttled = Response::from(
+            Builder::new()
+                .status(429)
+                .header(RETRY_AFTER, "0")
+                .body("")
+                .unwrap(),
+        );
+        let unavailable = Response::from(Builder::new().status(503).body("").unwrap());
+        let bad_gateway = Response::from(
+            Builder::new()
+                .status(502)
+                .header(RETRY_AFTER, "0")
+                .body("")
+                .unwrap(),
+        );
+
+        assert_eq!(policy.retry_rejected(1, &throttled), Some(Duration::ZERO));
+        assert_eq!(policy.retry_rejected(1, &unavailable), None);
+        assert_eq!(policy.retry_rejected(1, &bad_gateway), None);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{BoxedStream, EvgError};
+use futures::future;
+use futures::stream::{Stream, StreamExt};
+
+/// Adapters for streams of Evergreen API results.
+pub trait EvgStreamExt<T>: Stream<Item = Result<T, EvgError>> {
+    /// Discard errors, yielding only the successfully retrieved items.
+    fn ok_items(self) -> BoxedStream<T>;
+}
+
+impl<T: Send + 'static, S> EvgStreamExt<T> for S
+where
+    S: Stream<Item = Result<T, EvgError>> + Send + 'static,
+{
+    fn ok_items(self) -> BoxedStream<T> {
+        Box::pin(self.filter_map(|item| future::ready(item.ok())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn test_ok_items_should_drop_errors() {
+        let items = vec![
+            Ok(1),
+            Err(EvgError::NotFound {
+                url: String::from("url"),
+            }),
+            Ok(2),
+        ];
+
+        let result: Vec<i32> = stream::iter(items).ok_items().collect().await;
+
+        assert_eq!(result, vec![1, 2]);
+    }
+}
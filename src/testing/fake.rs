@@ -0,0 +1,1170 @@
+use crate::action;
+use crate::models::commit_queue::{EvgCommitQueue, EvgCommitQueueItem};
+use crate::models::distro::{EvgDistro, EvgTaskQueueItem};
+use crate::models::host::{EvgHost, EvgHostEvent, EvgHostFilter};
+use crate::models::ids::{BuildId, DistroId, HostId, PatchId, ProjectId, TaskId, VersionId};
+use crate::models::patch::{EvgPatchConfiguration, EvgVariantTasks};
+use crate::models::project::{EvgProject, EvgProjectAlias, EvgProjectVariables};
+use crate::models::stats::{EvgTaskStats, EvgTaskStatsRequest, EvgTestStats, EvgTestStatsRequest};
+use crate::models::status::{PatchStatus, TaskStatus};
+use crate::models::version::EvgVersion;
+use crate::models::{build::EvgBuild, patch::EvgPatch};
+use crate::models::{task::EvgTask, test::EvgTest};
+use crate::{ActionReport, EvgApiClient, EvgError, EvgStream};
+use async_stream::try_stream;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The `EvgApiClient` methods whose behavior can be customized on a `FakeEvgClient`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FakeMethod {
+    GetTask,
+    GetTaskExecution,
+    RestartTask,
+    AbortTask,
+    SetTaskActivated,
+    SetTaskPriority,
+    GetVersion,
+    GetBuild,
+    ActivateBuild,
+    RestartBuild,
+    AbortBuild,
+    SetBuildPriority,
+    RestartVersion,
+    AbortVersion,
+    GetTests,
+    GetExecutionTests,
+    GetTestStats,
+    GetTaskStats,
+    GetPatch,
+    AbortPatch,
+    RestartPatch,
+    SetPatchPriority,
+    ActivatePatch,
+    ConfigurePatch,
+    ListProjects,
+    GetProject,
+    GetProjectVariables,
+    GetProjectAliases,
+    GetCommitQueue,
+    EnqueuePatch,
+    DequeuePatch,
+    ListDistros,
+    GetDistro,
+    StreamDistroQueue,
+    GetHost,
+    GetHostEvents,
+    StreamHosts,
+    StreamVersions,
+    StreamUserPatches,
+    StreamProjectPatches,
+    StreamBuildTasks,
+    StreamLog,
+    StreamExecutionLog,
+    StreamTaskExecutions,
+    StreamTestLog,
+}
+
+type ErrorFactory = Arc<dyn Fn() -> EvgError + Send + Sync>;
+
+#[derive(Default)]
+struct FakeData {
+    tasks: HashMap<TaskId, EvgTask>,
+    task_executions: HashMap<(TaskId, u32), EvgTask>,
+    versions: HashMap<VersionId, EvgVersion>,
+    builds: HashMap<BuildId, EvgBuild>,
+    tests: HashMap<TaskId, Vec<EvgTest>>,
+    patches: Vec<EvgPatch>,
+    patch_priorities: HashMap<PatchId, i64>,
+    commit_queues: HashMap<ProjectId, Vec<EvgCommitQueueItem>>,
+    distros: HashMap<DistroId, EvgDistro>,
+    distro_queues: HashMap<DistroId, Vec<EvgTaskQueueItem>>,
+    hosts: HashMap<HostId, EvgHost>,
+    host_events: HashMap<HostId, Vec<EvgHostEvent>>,
+    projects: HashMap<ProjectId, EvgProject>,
+    project_variables: HashMap<ProjectId, EvgProjectVariables>,
+    project_aliases: HashMap<ProjectId, Vec<EvgProjectAlias>>,
+    test_stats: HashMap<ProjectId, Vec<EvgTestStats>>,
+    task_stats: HashMap<ProjectId, Vec<EvgTaskStats>>,
+    task_logs: HashMap<(TaskId, u32, String), Vec<String>>,
+    test_logs: HashMap<(TaskId, String), Vec<String>>,
+}
+
+impl FakeData {
+    /// Restart the given task, keeping a copy of the execution it replaces.
+    fn restart_task(&mut self, task_id: &TaskId) -> Option<EvgTask> {
+        let task = self.tasks.get_mut(task_id)?;
+        self.task_executions
+            .insert((task_id.clone(), task.execution), task.clone());
+        restart(task);
+        Some(task.clone())
+    }
+
+    /// Tests of the given execution of a task.
+    fn tests(&self, task_id: &TaskId, execution: u32) -> Vec<EvgTest> {
+        self.tests
+            .get(task_id)
+            .into_iter()
+            .flatten()
+            .filter(|test| test.execution == execution)
+            .cloned()
+            .collect()
+    }
+}
+
+/// An `EvgApiClient` serving seeded in-memory data without any network access.
+///
+/// Fixtures can be added at any time through a shared reference, so the client can be wrapped
+/// in an `Arc` and handed to the code under test. Errors and latency can be injected per method
+/// with `fail_with` and `set_latency`.
+#[derive(Default)]
+pub struct FakeEvgClient {
+    data: Mutex<FakeData>,
+    failures: Mutex<HashMap<FakeMethod, ErrorFactory>>,
+    latencies: Mutex<HashMap<FakeMethod, Duration>>,
+}
+
+impl FakeEvgClient {
+    /// Create a fake client with no data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a task, replacing any task with the same id.
+    pub fn insert_task(&self, task: EvgTask) {
+        self.data().tasks.insert(task.task_id.clone(), task);
+    }
+
+    /// Add a previous execution of a task, replacing any execution with the same number.
+    ///
+    /// The latest execution is the one added with `insert_task`.
+    pub fn insert_task_execution(&self, task: EvgTask) {
+        let key = (task.task_id.clone(), task.execution);
+        self.data().task_executions.insert(key, task);
+    }
+
+    /// Add a version, replacing any version with the same id.
+    pub fn insert_version(&self, version: EvgVersion) {
+        self.data()
+            .versions
+            .insert(version.version_id.clone(), version);
+    }
+
+    /// Add a build, replacing any build with the same id.
+    pub fn insert_build(&self, build: EvgBuild) {
+        self.data().builds.insert(build.id.clone(), build);
+    }
+
+    /// Add a test result to the task execution it belongs to.
+    pub fn insert_test(&self, test: EvgTest) {
+        self.data()
+            .tests
+            .entry(test.task_id.clone())
+            .or_default()
+            .push(test);
+    }
+
+    /// Add a patch, replacing any patch with the same id.
+    pub fn insert_patch(&self, patch: EvgPatch) {
+        let mut data = self.data();
+        data.patches.retain(|p| p.patch_id != patch.patch_id);
+        data.patches.push(patch);
+    }
+
+    /// Priority last set on the given patch through `set_patch_priority`.
+    pub fn patch_priority(&self, patch_id: &PatchId) -> Option<i64> {
+        self.data().patch_priorities.get(patch_id).copied()
+    }
+
+    /// Set the items of the commit queue of the given project.
+    pub fn set_commit_queue(&self, project_id: &ProjectId, items: Vec<EvgCommitQueueItem>) {
+        self.data().commit_queues.insert(project_id.clone(), items);
+    }
+
+    /// Add a distro, replacing any distro with the same name.
+    pub fn insert_distro(&self, distro: EvgDistro) {
+        self.data().distros.insert(distro.name.clone(), distro);
+    }
+
+    /// Set the task queue of the given distro, in dispatch order.
+    pub fn set_distro_queue(&self, distro_id: &DistroId, queue: Vec<EvgTaskQueueItem>) {
+        self.data().distro_queues.insert(distro_id.clone(), queue);
+    }
+
+    /// Add a host, replacing any host with the same id.
+    pub fn insert_host(&self, host: EvgHost) {
+        self.data().hosts.insert(host.host_id.clone(), host);
+    }
+
+    /// Set the event log of the given host.
+    pub fn set_host_events(&self, host_id: &HostId, events: Vec<EvgHostEvent>) {
+        self.data().host_events.insert(host_id.clone(), events);
+    }
+
+    /// Add a project, replacing any project with the same id.
+    pub fn insert_project(&self, project: EvgProject) {
+        self.data().projects.insert(project.id.clone(), project);
+    }
+
+    /// Set the variables of the given project.
+    pub fn set_project_variables(&self, project_id: &ProjectId, variables: EvgProjectVariables) {
+        self.data()
+            .project_variables
+            .insert(project_id.clone(), variables);
+    }
+
+    /// Set the aliases of the given project.
+    pub fn set_project_aliases(&self, project_id: &ProjectId, aliases: Vec<EvgProjectAlias>) {
+        self.data()
+            .project_aliases
+            .insert(project_id.clone(), aliases);
+    }
+
+    /// Set the test stats returned for the given project.
+    pub fn set_test_stats(&self, project_id: &ProjectId, stats: Vec<EvgTestStats>) {
+        self.data().test_stats.insert(project_id.clone(), stats);
+    }
+
+    /// Set the task stats returned for the given project.
+    pub fn set_task_stats(&self, project_id: &ProjectId, stats: Vec<EvgTaskStats>) {
+        self.data().task_stats.insert(project_id.clone(), stats);
+    }
+
+    /// Set the lines of the named log of the latest execution of the given task, or of its
+    /// first execution if the task has not been added.
+    pub fn set_task_log(&self, task_id: &TaskId, log_name: &str, lines: Vec<String>) {
+        let mut data = self.data();
+        let execution = data.tasks.get(task_id).map(|t| t.execution).unwrap_or(0);
+        let key = (task_id.clone(), execution, log_name.to_string());
+        data.task_logs.insert(key, lines);
+    }
+
+    /// Set the lines of the named log of the given task execution.
+    pub fn set_execution_log(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+        log_name: &str,
+        lines: Vec<String>,
+    ) {
+        let key = (task_id.clone(), execution, log_name.to_string());
+        self.data().task_logs.insert(key, lines);
+    }
+
+    /// Set the lines of the log of the given test.
+    pub fn set_test_log(&self, task_id: &TaskId, test_file: &str, lines: Vec<String>) {
+        self.data()
+            .test_logs
+            .insert((task_id.clone(), test_file.to_string()), lines);
+    }
+
+    /// Make every call to the given method fail with the error created by `error`.
+    pub fn fail_with<F>(&self, method: FakeMethod, error: F)
+    where
+        F: Fn() -> EvgError + Send + Sync + 'static,
+    {
+        self.failures
+            .lock()
+            .expect("fake client lock poisoned")
+            .insert(method, Arc::new(error));
+    }
+
+    /// Stop injecting errors into the given method.
+    pub fn clear_failure(&self, method: FakeMethod) {
+        self.failures
+            .lock()
+            .expect("fake client lock poisoned")
+            .remove(&method);
+    }
+
+    /// Delay every call to the given method by `latency`.
+    pub fn set_latency(&self, method: FakeMethod, latency: Duration) {
+        self.latencies
+            .lock()
+            .expect("fake client lock poisoned")
+            .insert(method, latency);
+    }
+
+    fn data(&self) -> std::sync::MutexGuard<'_, FakeData> {
+        self.data.lock().expect("fake client lock poisoned")
+    }
+
+    fn latency(&self, method: FakeMethod) -> Option<Duration> {
+        self.latencies
+            .lock()
+            .expect("fake client lock poisoned")
+            .get(&method)
+            .copied()
+    }
+
+    fn failure(&self, method: FakeMethod) -> Option<EvgError> {
+        self.failures
+            .lock()
+            .expect("fake client lock poisoned")
+            .get(&method)
+            .map(|error| error())
+    }
+
+    /// Apply `update` to the given task, returning the updated task.
+    async fn update_task<F>(
+        &self,
+        method: FakeMethod,
+        task_id: &TaskId,
+        update: F,
+    ) -> Result<EvgTask, EvgError>
+    where
+        F: FnOnce(&mut EvgTask) + Send,
+    {
+        self.enter(method).await?;
+        let mut data = self.data();
+        let task = data
+            .tasks
+            .get_mut(task_id)
+            .ok_or_else(|| not_found("tasks", task_id.as_str()))?;
+        update(task);
+        Ok(task.clone())
+    }
+
+    /// Stream the named log of the given task execution.
+    fn stream_task_log(
+        &self,
+        method: FakeMethod,
+        task_id: &TaskId,
+        execution: u32,
+        log_name: &str,
+    ) -> EvgStream<String> {
+        let key = (task_id.clone(), execution, log_name.to_string());
+        let lines = self.data().task_logs.get(&key).cloned();
+        let lines = lines.ok_or_else(|| EvgError::MissingLog {
+            task_id: task_id.clone(),
+            log_name: log_name.to_string(),
+        });
+        self.stream_result(method, lines)
+    }
+
+    /// Apply `update` to each of the given tasks.
+    fn update_tasks<F: Fn(&mut EvgTask)>(&self, task_ids: &[TaskId], update: F) {
+        let mut data = self.data();
+        for task_id in task_ids {
+            if let Some(task) = data.tasks.get_mut(task_id) {
+                update(task);
+            }
+        }
+    }
+
+    /// Apply `update` to the given patch, returning the updated patch.
+    async fn update_patch<F>(
+        &self,
+        method: FakeMethod,
+        patch_id: &PatchId,
+        update: F,
+    ) -> Result<EvgPatch, EvgError>
+    where
+        F: FnOnce(&mut EvgPatch) + Send,
+    {
+        self.enter(method).await?;
+        let mut data = self.data();
+        let patch = data
+            .patches
+            .iter_mut()
+            .find(|p| &p.patch_id == patch_id)
+            .ok_or_else(|| not_found("patches", patch_id.as_str()))?;
+        update(patch);
+        Ok(patch.clone())
+    }
+
+    /// Apply the latency and failure configured for the given method.
+    async fn enter(&self, method: FakeMethod) -> Result<(), EvgError> {
+        if let Some(latency) = self.latency(method) {
+            tokio::time::sleep(latency).await;
+        }
+        match self.failure(method) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Stream the given items, applying the latency and failure configured for the method.
+    fn stream<T: Send + 'static>(&self, method: FakeMethod, items: Vec<T>) -> EvgStream<T> {
+        self.stream_result(method, Ok(items))
+    }
+
+    /// Stream the given items, or fail with the given error, once the latency and failure
+    /// configured for the method have been applied.
+    fn stream_result<T: Send + 'static>(
+        &self,
+        method: FakeMethod,
+        items: Result<Vec<T>, EvgError>,
+    ) -> EvgStream<T> {
+        let latency = self.latency(method);
+        let failure = self.failure(method);
+
+        Box::pin(try_stream! {
+            if let Some(latency) = latency {
+                tokio::time::sleep(latency).await;
+            }
+            if let Some(err) = failure {
+                Err(err)?;
+            }
+            for item in items? {
+                yield item;
+            }
+        })
+    }
+}
+
+/// Reset a task as evergreen does when it is restarted.
+fn restart(task: &mut EvgTask) {
+    task.status = TaskStatus::Undispatched;
+    task.activated = true;
+    task.execution += 1;
+    task.restarts = Some(task.restarts.unwrap_or(0) + 1);
+    task.start_time = None;
+    task.finish_time = None;
+}
+
+fn not_found(endpoint: &str, id: &str) -> EvgError {
+    EvgError::NotFound {
+        url: format!("/rest/v2/{}/{}", endpoint, id),
+    }
+}
+
+#[async_trait]
+impl EvgApiClient for FakeEvgClient {
+    async fn get_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError> {
+        self.enter(FakeMethod::GetTask).await?;
+        let task = self.data().tasks.get(task_id).cloned();
+        task.ok_or_else(|| not_found("tasks", task_id.as_str()))
+    }
+
+    async fn get_task_execution(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<EvgTask, EvgError> {
+        self.enter(FakeMethod::GetTaskExecution).await?;
+        let data = self.data();
+        let task = data
+            .tasks
+            .get(task_id)
+            .filter(|t| t.execution == execution)
+            .or_else(|| data.task_executions.get(&(task_id.clone(), execution)));
+        task.cloned()
+            .ok_or_else(|| not_found("tasks", task_id.as_str()))
+    }
+
+    fn stream_task_executions(&self, task_id: &TaskId) -> EvgStream<EvgTask> {
+        let mut executions: Vec<EvgTask> = {
+            let data = self.data();
+            let latest = data.tasks.get(task_id);
+            let previous = data
+                .task_executions
+                .values()
+                .filter(|t| &t.task_id == task_id)
+                .filter(|t| latest.map(|l| t.execution < l.execution).unwrap_or(true));
+            previous.chain(latest).cloned().collect()
+        };
+        executions.sort_by_key(|t| t.execution);
+        self.stream(FakeMethod::StreamTaskExecutions, executions)
+    }
+
+    /// Resets the task to undispatched as a new execution. For a display task, its execution
+    /// tasks are reset too, only the failed ones if `failed_only` is set.
+    async fn restart_task(&self, task_id: &TaskId, failed_only: bool) -> Result<EvgTask, EvgError> {
+        self.enter(FakeMethod::RestartTask).await?;
+        let mut data = self.data();
+        let task = data
+            .restart_task(task_id)
+            .ok_or_else(|| not_found("tasks", task_id.as_str()))?;
+        for execution_task_id in task.execution_tasks.iter().flatten() {
+            if let Some(execution_task) = data.tasks.get(execution_task_id) {
+                if !failed_only || execution_task.status.is_failure() {
+                    data.restart_task(execution_task_id);
+                }
+            }
+        }
+        Ok(task)
+    }
+
+    async fn abort_task(&self, task_id: &TaskId) -> Result<EvgTask, EvgError> {
+        self.update_task(FakeMethod::AbortTask, task_id, |t| {
+            t.status = TaskStatus::Aborted;
+        })
+        .await
+    }
+
+    async fn set_task_activated(
+        &self,
+        task_id: &TaskId,
+        activated: bool,
+    ) -> Result<EvgTask, EvgError> {
+        self.update_task(FakeMethod::SetTaskActivated, task_id, |t| {
+            t.activated = activated;
+        })
+        .await
+    }
+
+    async fn set_task_priority(
+        &self,
+        task_id: &TaskId,
+        priority: i64,
+    ) -> Result<EvgTask, EvgError> {
+        self.update_task(FakeMethod::SetTaskPriority, task_id, |t| {
+            t.priority = priority;
+        })
+        .await
+    }
+
+    async fn get_version(&self, version_id: &VersionId) -> Result<EvgVersion, EvgError> {
+        self.enter(FakeMethod::GetVersion).await?;
+        let version = self.data().versions.get(version_id).cloned();
+        version.ok_or_else(|| not_found("versions", version_id.as_str()))
+    }
+
+    async fn get_build(&self, build_id: &BuildId) -> Result<Option<EvgBuild>, EvgError> {
+        self.enter(FakeMethod::GetBuild).await?;
+        let build = self.data().builds.get(build_id).cloned();
+        Ok(build)
+    }
+
+    async fn activate_build(
+        &self,
+        build_id: &BuildId,
+        activated: bool,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        self.enter(FakeMethod::ActivateBuild).await?;
+        let affected_tasks = action::build_tasks_matching(self, build_id, |t| {
+            action::is_activation_affected(t, activated)
+        })
+        .await?;
+        if !dry_run {
+            self.update_tasks(&affected_tasks, |t| t.activated = activated);
+            if let Some(build) = self.data().builds.get_mut(build_id) {
+                build.activated = activated;
+            }
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn restart_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        self.enter(FakeMethod::RestartBuild).await?;
+        let affected_tasks =
+            action::build_tasks_matching(self, build_id, action::is_restart_affected).await?;
+        if dry_run {
+            return Ok(ActionReport::new(dry_run, affected_tasks));
+        }
+        Ok(action::restart_tasks(self, affected_tasks).await)
+    }
+
+    async fn abort_build(
+        &self,
+        build_id: &BuildId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        self.enter(FakeMethod::AbortBuild).await?;
+        let affected_tasks =
+            action::build_tasks_matching(self, build_id, action::is_abort_affected).await?;
+        if !dry_run {
+            self.update_tasks(&affected_tasks, |t| t.status = TaskStatus::Aborted);
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn set_build_priority(
+        &self,
+        build_id: &BuildId,
+        priority: i64,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        self.enter(FakeMethod::SetBuildPriority).await?;
+        let affected_tasks =
+            action::build_tasks_matching(self, build_id, action::is_priority_affected).await?;
+        if !dry_run {
+            self.update_tasks(&affected_tasks, |t| t.priority = priority);
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn restart_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        self.enter(FakeMethod::RestartVersion).await?;
+        let affected_tasks =
+            action::version_tasks_matching(self, version_id, action::is_restart_affected).await?;
+        if dry_run {
+            return Ok(ActionReport::new(dry_run, affected_tasks));
+        }
+        Ok(action::restart_tasks(self, affected_tasks).await)
+    }
+
+    async fn abort_version(
+        &self,
+        version_id: &VersionId,
+        dry_run: bool,
+    ) -> Result<ActionReport, EvgError> {
+        self.enter(FakeMethod::AbortVersion).await?;
+        let affected_tasks =
+            action::version_tasks_matching(self, version_id, action::is_abort_affected).await?;
+        if !dry_run {
+            self.update_tasks(&affected_tasks, |t| t.status = TaskStatus::Aborted);
+        }
+        Ok(ActionReport::new(dry_run, affected_tasks))
+    }
+
+    async fn get_tests(&self, task_id: &TaskId) -> Result<Vec<EvgTest>, EvgError> {
+        self.enter(FakeMethod::GetTests).await?;
+        let data = self.data();
+        let execution = data.tasks.get(task_id).map(|t| t.execution).unwrap_or(0);
+        Ok(data.tests(task_id, execution))
+    }
+
+    async fn get_execution_tests(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+    ) -> Result<Vec<EvgTest>, EvgError> {
+        self.enter(FakeMethod::GetExecutionTests).await?;
+        Ok(self.data().tests(task_id, execution))
+    }
+
+    /// Returns all test stats set for the project; the query is ignored.
+    async fn get_test_stats(
+        &self,
+        project_id: &ProjectId,
+        _query: &EvgTestStatsRequest,
+    ) -> Result<Vec<EvgTestStats>, EvgError> {
+        self.enter(FakeMethod::GetTestStats).await?;
+        let stats = self.data().test_stats.get(project_id).cloned();
+        Ok(stats.unwrap_or_default())
+    }
+
+    /// Returns all task stats set for the project; the query is ignored.
+    async fn get_task_stats(
+        &self,
+        project_id: &ProjectId,
+        _query: &EvgTaskStatsRequest,
+    ) -> Result<Vec<EvgTaskStats>, EvgError> {
+        self.enter(FakeMethod::GetTaskStats).await?;
+        let stats = self.data().task_stats.get(project_id).cloned();
+        Ok(stats.unwrap_or_default())
+    }
+
+    async fn get_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        self.update_patch(FakeMethod::GetPatch, patch_id, |_| {})
+            .await
+    }
+
+    /// Marks the patch as failed, which is how evergreen reports an aborted patch.
+    async fn abort_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        self.update_patch(FakeMethod::AbortPatch, patch_id, |p| {
+            p.status = PatchStatus::Failed;
+        })
+        .await
+    }
+
+    /// Marks the patch as started again, clearing its finish time.
+    async fn restart_patch(&self, patch_id: &PatchId) -> Result<EvgPatch, EvgError> {
+        self.update_patch(FakeMethod::RestartPatch, patch_id, |p| {
+            p.status = PatchStatus::Started;
+            p.finish_time = None;
+        })
+        .await
+    }
+
+    /// Records the priority, which can be read back with `patch_priority`.
+    async fn set_patch_priority(
+        &self,
+        patch_id: &PatchId,
+        priority: i64,
+    ) -> Result<EvgPatch, EvgError> {
+        let patch = self
+            .update_patch(FakeMethod::SetPatchPriority, patch_id, |_| {})
+            .await?;
+        self.data()
+            .patch_priorities
+            .insert(patch_id.clone(), priority);
+        Ok(patch)
+    }
+
+    async fn activate_patch(
+        &self,
+        patch_id: &PatchId,
+        activated: bool,
+    ) -> Result<EvgPatch, EvgError> {
+        self.update_patch(FakeMethod::ActivatePatch, patch_id, |p| {
+            p.activated = activated;
+        })
+        .await
+    }
+
+    /// Replaces the variants and tasks of the patch and activates it.
+    async fn configure_patch(
+        &self,
+        patch_id: &PatchId,
+        configuration: &EvgPatchConfiguration,
+    ) -> Result<EvgPatch, EvgError> {
+        self.update_patch(FakeMethod::ConfigurePatch, patch_id, |p| {
+            if let Some(description) = &configuration.description {
+                p.description = description.clone();
+            }
+            p.variants_tasks = Some(
+                configuration
+                    .variants
+                    .iter()
+                    .map(|v| EvgVariantTasks {
+                        name: v.variant.clone(),
+                        tasks: v.tasks.clone(),
+                        display_tasks: vec![],
+                    })
+                    .collect(),
+            );
+            p.activated = true;
+        })
+        .await
+    }
+
+    async fn list_projects(&self) -> Result<Vec<EvgProject>, EvgError> {
+        self.enter(FakeMethod::ListProjects).await?;
+        let mut projects: Vec<EvgProject> = self.data().projects.values().cloned().collect();
+        projects.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(projects)
+    }
+
+    /// Finds the project by id or identifier.
+    async fn get_project(&self, project_id: &ProjectId) -> Result<EvgProject, EvgError> {
+        self.enter(FakeMethod::GetProject).await?;
+        let project = self
+            .data()
+            .projects
+            .values()
+            .find(|p| &p.id == project_id || p.identifier == project_id.as_str())
+            .cloned();
+        project.ok_or_else(|| not_found("projects", project_id.as_str()))
+    }
+
+    async fn get_project_variables(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<EvgProjectVariables, EvgError> {
+        self.enter(FakeMethod::GetProjectVariables).await?;
+        let variables = self.data().project_variables.get(project_id).cloned();
+        Ok(variables.unwrap_or_default().masked())
+    }
+
+    async fn get_project_aliases(
+        &self,
+        project_id: &ProjectId,
+    ) -> Result<Vec<EvgProjectAlias>, EvgError> {
+        self.enter(FakeMethod::GetProjectAliases).await?;
+        let aliases = self.data().project_aliases.get(project_id).cloned();
+        Ok(aliases.unwrap_or_default())
+    }
+
+    async fn get_host(&self, host_id: &HostId) -> Result<EvgHost, EvgError> {
+        self.enter(FakeMethod::GetHost).await?;
+        let host = self.data().hosts.get(host_id).cloned();
+        host.ok_or_else(|| not_found("hosts", host_id.as_str()))
+    }
+
+    async fn get_host_events(&self, host_id: &HostId) -> Result<Vec<EvgHostEvent>, EvgError> {
+        self.enter(FakeMethod::GetHostEvents).await?;
+        let events = self.data().host_events.get(host_id).cloned();
+        Ok(events.unwrap_or_default())
+    }
+
+    async fn get_commit_queue(&self, project_id: &ProjectId) -> Result<EvgCommitQueue, EvgError> {
+        self.enter(FakeMethod::GetCommitQueue).await?;
+        let items = self.data().commit_queues.get(project_id).cloned();
+        Ok(EvgCommitQueue {
+            project_id: project_id.clone(),
+            queue: Some(items.unwrap_or_default()),
+        })
+    }
+
+    /// Queues a seeded patch behind the item being merged when `force` is set.
+    async fn enqueue_patch(&self, patch_id: &PatchId, force: bool) -> Result<usize, EvgError> {
+        self.enter(FakeMethod::EnqueuePatch).await?;
+        let mut data = self.data();
+        let patch = data
+            .patches
+            .iter()
+            .find(|p| &p.patch_id == patch_id)
+            .cloned()
+            .ok_or_else(|| not_found("patches", patch_id.as_str()))?;
+        let queue = data
+            .commit_queues
+            .entry(patch.project_id.clone())
+            .or_default();
+        let item = EvgCommitQueueItem {
+            issue: patch_id.to_string(),
+            version: None,
+            enqueue_time: Some(chrono::Utc::now()),
+            source: Some(String::from("diff")),
+            patch: Some(patch),
+        };
+        let position = if force {
+            queue.len().min(1)
+        } else {
+            queue.len()
+        };
+        queue.insert(position, item);
+        Ok(position)
+    }
+
+    async fn dequeue_patch(
+        &self,
+        project_id: &ProjectId,
+        patch_id: &PatchId,
+    ) -> Result<(), EvgError> {
+        self.enter(FakeMethod::DequeuePatch).await?;
+        let mut data = self.data();
+        let queue = data.commit_queues.entry(project_id.clone()).or_default();
+        let position = queue
+            .iter()
+            .position(|i| i.issue == patch_id.as_str())
+            .ok_or_else(|| not_found("commit_queue", patch_id.as_str()))?;
+        queue.remove(position);
+        Ok(())
+    }
+
+    async fn list_distros(&self) -> Result<Vec<EvgDistro>, EvgError> {
+        self.enter(FakeMethod::ListDistros).await?;
+        let mut distros: Vec<EvgDistro> = self.data().distros.values().cloned().collect();
+        distros.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(distros)
+    }
+
+    async fn get_distro(&self, distro_id: &DistroId) -> Result<EvgDistro, EvgError> {
+        self.enter(FakeMethod::GetDistro).await?;
+        let distro = self.data().distros.get(distro_id).cloned();
+        distro.ok_or_else(|| not_found("distros", distro_id.as_str()))
+    }
+
+    fn stream_hosts(&self, filter: &EvgHostFilter) -> EvgStream<EvgHost> {
+        let mut hosts: Vec<EvgHost> = self
+            .data()
+            .hosts
+            .values()
+            .filter(|h| {
+                filter
+                    .status
+                    .as_ref()
+                    .map(|s| &h.status == s)
+                    .unwrap_or(true)
+            })
+            .filter(|h| {
+                filter
+                    .distro_id
+                    .as_ref()
+                    .map(|d| &h.distro.distro_id == d)
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect();
+        hosts.sort_by(|a, b| a.host_id.cmp(&b.host_id));
+        self.stream(FakeMethod::StreamHosts, hosts)
+    }
+
+    fn stream_distro_queue(&self, distro_id: &DistroId) -> EvgStream<EvgTaskQueueItem> {
+        let queue = self.data().distro_queues.get(distro_id).cloned();
+        self.stream(FakeMethod::StreamDistroQueue, queue.unwrap_or_default())
+    }
+
+    fn stream_versions(&self, project_id: &ProjectId) -> EvgStream<EvgVersion> {
+        let mut versions: Vec<EvgVersion> = self
+            .data()
+            .versions
+            .values()
+            .filter(|v| &v.project == project_id)
+            .cloned()
+            .collect();
+        versions.sort_by_key(|v| std::cmp::Reverse(v.order));
+        self.stream(FakeMethod::StreamVersions, versions)
+    }
+
+    fn stream_user_patches(&self, user_id: &str, limit: Option<usize>) -> EvgStream<EvgPatch> {
+        let patches: Vec<EvgPatch> = self
+            .data()
+            .patches
+            .iter()
+            .filter(|p| p.author == user_id)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        self.stream(FakeMethod::StreamUserPatches, patches)
+    }
+
+    fn stream_project_patches(
+        &self,
+        project_id: &ProjectId,
+        limit: Option<usize>,
+    ) -> EvgStream<EvgPatch> {
+        let patches: Vec<EvgPatch> = self
+            .data()
+            .patches
+            .iter()
+            .filter(|p| &p.project_id == project_id || p.project_identifier == project_id.as_str())
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        self.stream(FakeMethod::StreamProjectPatches, patches)
+    }
+
+    fn stream_build_tasks(&self, build_id: &BuildId, status: Option<&str>) -> EvgStream<EvgTask> {
+        let mut tasks: Vec<EvgTask> = self
+            .data()
+            .tasks
+            .values()
+            .filter(|t| &t.build_id == build_id)
+            .filter(|t| status.map(|s| t.status == s).unwrap_or(true))
+            .cloned()
+            .collect();
+        tasks.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+        self.stream(FakeMethod::StreamBuildTasks, tasks)
+    }
+
+    fn stream_log(&self, task: &EvgTask, log_name: &str) -> EvgStream<String> {
+        self.stream_task_log(
+            FakeMethod::StreamLog,
+            &task.task_id,
+            task.execution,
+            log_name,
+        )
+    }
+
+    fn stream_execution_log(
+        &self,
+        task_id: &TaskId,
+        execution: u32,
+        log_name: &str,
+    ) -> EvgStream<String> {
+        self.stream_task_log(FakeMethod::StreamExecutionLog, task_id, execution, log_name)
+    }
+
+    fn stream_test_log(&self, test: &EvgTest) -> EvgStream<String> {
+        let key = (test.task_id.clone(), test.test_file.clone());
+        let lines = self.data().test_logs.get(&key).cloned();
+        let lines = lines.ok_or_else(|| EvgError::MissingLog {
+            task_id: test.task_id.clone(),
+            log_name: test.test_file.clone(),
+        });
+        self.stream_result(FakeMethod::StreamTestLog, lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use futures::StreamExt;
+    use reqwest::StatusCode;
+
+    #[tokio::test]
+    async fn test_get_task_should_return_seeded_task() {
+        let client = FakeEvgClient::new();
+        client.insert_task(fixtures::task("task_0", "build", "success"));
+
+        let found = client.get_task(&"task_0".into()).await.unwrap();
+        let missing = client.get_task(&"task_1".into()).await;
+
+        assert_eq!(found.task_id, "task_0");
+        assert!(missing.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_stream_build_tasks_should_filter_by_build_and_status() {
+        let client = FakeEvgClient::new();
+        client.insert_task(fixtures::task("task_0", "build", "success"));
+        client.insert_task(fixtures::task("task_1", "build", "failed"));
+        client.insert_task(fixtures::task("task_2", "other_build", "failed"));
+
+        let tasks: Vec<TaskId> = client
+            .stream_build_tasks(&"build".into(), Some("failed"))
+            .map(|t| t.unwrap().task_id)
+            .collect()
+            .await;
+
+        assert_eq!(tasks, vec!["task_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_restart_task_should_only_restart_failed_execution_tasks() {
+        let client = FakeEvgClient::new();
+        let mut display_task = fixtures::task("display", "build", "failed");
+        display_task.display_only = true;
+        display_task.execution_tasks = Some(vec!["task_0".into(), "task_1".into()]);
+        client.insert_task(display_task);
+        client.insert_task(fixtures::task("task_0", "build", "success"));
+        client.insert_task(fixtures::task("task_1", "build", "failed"));
+
+        let restarted = client.restart_task(&"display".into(), true).await.unwrap();
+        let passed = client.get_task(&"task_0".into()).await.unwrap();
+        let failed = client.get_task(&"task_1".into()).await.unwrap();
+
+        assert_eq!(restarted.status, TaskStatus::Undispatched);
+        assert_eq!(restarted.execution, 1);
+        assert_eq!(passed.execution, 0);
+        assert_eq!(failed.status, TaskStatus::Undispatched);
+    }
+
+    #[tokio::test]
+    async fn test_restart_task_should_keep_previous_execution() {
+        let client = FakeEvgClient::new();
+        let task_id = TaskId::from("task_0");
+        client.insert_task(fixtures::task("task_0", "build", "failed"));
+        client.set_task_log(&task_id, "task_log", vec!["first run".to_string()]);
+
+        client.restart_task(&task_id, false).await.unwrap();
+        client.set_execution_log(&task_id, 1, "task_log", vec!["second run".to_string()]);
+        let first = client.get_task_execution(&task_id, 0).await.unwrap();
+        let executions: Vec<u32> = client
+            .stream_task_executions(&task_id)
+            .map(|t| t.unwrap().execution)
+            .collect()
+            .await;
+        let log: Vec<String> = client
+            .stream_execution_log(&task_id, 0, "task_log")
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        let latest = client.get_task(&task_id).await.unwrap();
+        let latest_log: Vec<String> = client
+            .stream_log(&latest, "task_log")
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(first.status, TaskStatus::Failed);
+        assert_eq!(executions, vec![0, 1]);
+        assert_eq!(log, vec!["first run"]);
+        assert_eq!(latest_log, vec!["second run"]);
+        assert!(client.get_task_execution(&task_id, 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_test_log_should_fail_for_missing_log() {
+        let client = FakeEvgClient::new();
+        let test: EvgTest = fixtures::test("task_0", "test_0", "pass");
+        let other: EvgTest = fixtures::test("task_0", "test_1", "pass");
+        client.set_test_log(&"task_0".into(), "test_0", vec!["line".to_string()]);
+
+        let log: Vec<String> = client
+            .stream_test_log(&test)
+            .map(|l| l.unwrap())
+            .collect()
+            .await;
+        let missing: Vec<Result<String, EvgError>> = client.stream_test_log(&other).collect().await;
+
+        assert_eq!(log, vec!["line"]);
+        assert!(matches!(
+            &missing[..],
+            [Err(EvgError::MissingLog { log_name, .. })] if log_name == "test_1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_missing_log_should_apply_injected_failure_first() {
+        let client = FakeEvgClient::new();
+        let task = fixtures::task("task_0", "build", "success");
+        client.fail_with(FakeMethod::StreamLog, || EvgError::NotFound {
+            url: "log".to_string(),
+        });
+
+        let results: Vec<Result<String, EvgError>> =
+            client.stream_log(&task, "task_log").collect().await;
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].as_ref().unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_restart_build_should_only_report_on_dry_run() {
+        let client = FakeEvgClient::new();
+        client.insert_task(fixtures::task("task_0", "build", "success"));
+        client.insert_task(fixtures::task("task_1", "build", "failed"));
+        let build_id = BuildId::from("build");
+
+        let planned = client.restart_build(&build_id, true).await.unwrap();
+        let unchanged = client.get_task(&"task_1".into()).await.unwrap();
+        let applied = client.restart_build(&build_id, false).await.unwrap();
+        let restarted = client.get_task(&"task_1".into()).await.unwrap();
+
+        assert_eq!(planned.affected_tasks, vec!["task_1"]);
+        assert_eq!(unchanged.status, TaskStatus::Failed);
+        assert_eq!(applied.affected_tasks, planned.affected_tasks);
+        assert_eq!(restarted.status, TaskStatus::Undispatched);
+    }
+
+    #[tokio::test]
+    async fn test_patch_actions_should_update_seeded_patch() {
+        let client = FakeEvgClient::new();
+        client.insert_patch(fixtures::patch("patch_0", "started"));
+        let patch_id = PatchId::from("patch_0");
+
+        let activated = client.activate_patch(&patch_id, true).await.unwrap();
+        let aborted = client.abort_patch(&patch_id).await.unwrap();
+        client.set_patch_priority(&patch_id, 50).await.unwrap();
+        let missing = client.get_patch(&"patch_1".into()).await;
+
+        assert!(activated.activated);
+        assert_eq!(aborted.status, PatchStatus::Failed);
+        assert_eq!(client.patch_priority(&patch_id), Some(50));
+        assert!(missing.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_commit_queue_should_track_enqueued_patches() {
+        let client = FakeEvgClient::new();
+        let first: EvgPatch = fixtures::patch("patch_0", "created");
+        let second: EvgPatch = fixtures::patch("patch_1", "created");
+        client.insert_patch(first.clone());
+        client.insert_patch(second.clone());
+
+        client.enqueue_patch(&first.patch_id, false).await.unwrap();
+        let position = client.enqueue_patch(&second.patch_id, false).await.unwrap();
+        client
+            .dequeue_patch(&first.project_id, &first.patch_id)
+            .await
+            .unwrap();
+
+        assert_eq!(position, 1);
+        assert_eq!(
+            client.get_commit_queue_position(&second).await.unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            client.get_commit_queue_position(&first).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_with_should_inject_errors() {
+        let client = FakeEvgClient::new();
+        client.insert_task(fixtures::task("task_0", "build", "success"));
+        client.fail_with(FakeMethod::GetTask, || EvgError::HttpStatus {
+            url: String::from("/rest/v2/tasks/task_0"),
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            body: String::new(),
+        });
+
+        let failed = client.get_task(&"task_0".into()).await;
+        client.clear_failure(FakeMethod::GetTask);
+        let recovered = client.get_task(&"task_0".into()).await;
+
+        assert_eq!(
+            failed.unwrap_err().status(),
+            Some(StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert!(recovered.is_ok());
+    }
+}
@@ -0,0 +1,168 @@
+//! JSON fixtures, and the models parsed from them, shared by the tests in this crate.
+use crate::models::build::EvgBuild;
+use crate::models::patch::EvgPatch;
+use crate::models::task::EvgTask;
+use crate::models::test::EvgTest;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// Parse a JSON fixture, such as one of the `_json` fixtures with some fields changed.
+pub(crate) fn parse<T: DeserializeOwned>(json: Value) -> T {
+    serde_json::from_value(json).expect("fixture should parse")
+}
+
+pub(crate) fn task(task_id: &str, build_id: &str, status: &str) -> EvgTask {
+    parse(task_json(task_id, build_id, status))
+}
+
+pub(crate) fn finished_task(task_id: &str, build_id: &str, status: &str) -> EvgTask {
+    let mut json = task_json(task_id, build_id, status);
+    json["finish_time"] = json!("2021-01-01T01:00:00Z");
+    parse(json)
+}
+
+pub(crate) fn build(build_id: &str, version_id: &str, status: &str) -> EvgBuild {
+    parse(build_json(build_id, version_id, status))
+}
+
+pub(crate) fn patch(patch_id: &str, status: &str) -> EvgPatch {
+    parse(patch_json(patch_id, status))
+}
+
+pub(crate) fn test(task_id: &str, test_file: &str, status: &str) -> EvgTest {
+    parse(test_json(task_id, test_file, status))
+}
+
+pub(crate) fn task_json(task_id: &str, build_id: &str, status: &str) -> Value {
+    json!({
+        "activated": true,
+        "activated_by": "user",
+        "build_id": build_id,
+        "build_variant": "variant",
+        "create_time": "2021-01-01T00:00:00Z",
+        "display_name": task_id,
+        "display_only": false,
+        "distro_id": "distro",
+        "est_wait_to_start_ms": 0,
+        "execution": 0,
+        "expected_duration_ms": 0,
+        "generate_task": false,
+        "generated_by": "",
+        "host_id": "host",
+        "logs": {},
+        "order": 1,
+        "project_id": "project",
+        "priority": 0,
+        "revision": "abc123",
+        "status": status,
+        "status_details": {
+            "status": status,
+            "type": "test",
+            "desc": "",
+            "timed_out": false
+        },
+        "task_id": task_id,
+        "time_taken_ms": 0,
+        "version_id": "version"
+    })
+}
+
+pub(crate) fn version_json(version_id: &str, order: u64) -> Value {
+    json!({
+        "version_id": version_id,
+        "create_time": "2021-01-01T00:00:00Z",
+        "revision": "abc123",
+        "order": order,
+        "project": "project",
+        "author": "author",
+        "author_email": "author@example.com",
+        "message": "commit message",
+        "status": "success",
+        "repo": "repo",
+        "branch": "main"
+    })
+}
+
+pub(crate) fn build_json(build_id: &str, version_id: &str, status: &str) -> Value {
+    json!({
+        "id": build_id,
+        "project_id": "project",
+        "create_time": "2021-01-01T00:00:00Z",
+        "version": version_id,
+        "git_hash": "abc123",
+        "build_variant": build_id,
+        "status": status,
+        "activated": true,
+        "activated_by": "user",
+        "order": 1,
+        "tasks": [],
+        "time_taken_ms": 0,
+        "display_name": build_id,
+        "predicted_makespan_ms": 0,
+        "actual_makespan_ms": 0,
+        "origin": "project",
+        "status_counts": {
+            "succeeded": 0,
+            "failed": 0,
+            "started": 0,
+            "undispatched": 0,
+            "dispatched": 0,
+            "timed_out": 0
+        }
+    })
+}
+
+pub(crate) fn patch_json(patch_id: &str, status: &str) -> Value {
+    json!({
+        "patch_id": patch_id,
+        "description": "patch description",
+        "project_id": "project",
+        "project_identifier": "project",
+        "branch": "main",
+        "git_hash": "abc123",
+        "patch_number": 1,
+        "author": "author",
+        "version": patch_id,
+        "status": status,
+        "create_time": "2021-01-01T00:00:00Z",
+        "activated": false,
+        "variants_tasks": [{ "name": "variant", "tasks": ["compile"] }]
+    })
+}
+
+pub(crate) fn project_json(project_id: &str, enabled: bool) -> Value {
+    json!({
+        "id": project_id,
+        "identifier": project_id,
+        "display_name": project_id,
+        "owner_name": "owner",
+        "repo_name": "repo",
+        "branch_name": "main",
+        "enabled": enabled
+    })
+}
+
+pub(crate) fn host_json(host_id: &str, distro_id: &str, status: &str) -> Value {
+    json!({
+        "host_id": host_id,
+        "host_url": format!("{}.example.com", host_id),
+        "distro": { "distro_id": distro_id, "provider": "ec2" },
+        "provisioned": true,
+        "started_by": "mci",
+        "status": status,
+        "user_host": false
+    })
+}
+
+pub(crate) fn test_json(task_id: &str, test_file: &str, status: &str) -> Value {
+    json!({
+        "task_id": task_id,
+        "status": status,
+        "test_file": test_file,
+        "exit_code": 0,
+        "start_time": "2021-01-01T00:00:00Z",
+        "end_time": "2021-01-01T00:01:00Z",
+        "logs": { "url": "", "line_num": 0, "url_raw": "" },
+        "duration": 60.0
+    })
+}
@@ -0,0 +1,10 @@
+//! Test doubles for code that talks to Evergreen.
+//!
+//! Enable the `testing` feature to use them.
+mod fake;
+#[cfg(test)]
+pub(crate) mod fixtures;
+mod stub_server;
+
+pub use fake::{FakeEvgClient, FakeMethod};
+pub use stub_server::{RecordedRequest, StubServer};
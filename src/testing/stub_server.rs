@@ -0,0 +1,292 @@
+use crate::EvgClient;
+use hyper::header::{CONTENT_TYPE, LINK, RETRY_AFTER};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Query parameter used to select a page of a paginated route.
+const PAGE_PARAM: &str = "page";
+
+#[derive(Debug, Clone)]
+enum StubResponse {
+    Json(String),
+    Pages(Vec<String>),
+    Text(String),
+    Status(StatusCode, String),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Throttle {
+    remaining: usize,
+    retry_after: Option<u64>,
+}
+
+/// A request received by a `StubServer`.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    /// Query parameters, in the order they were sent.
+    pub query: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// Value of the first query parameter with the given name.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The body parsed as JSON, or `Value::Null` if it is empty or not JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+#[derive(Debug, Default)]
+struct StubState {
+    routes: HashMap<String, StubResponse>,
+    throttles: HashMap<String, Throttle>,
+    received: Vec<RecordedRequest>,
+}
+
+/// A local HTTP server serving canned Evergreen API responses.
+///
+/// Routes are matched on the request path only; the method and query string do not select a
+/// route, except for the page parameter used to paginate routes added with `add_pages`. Every
+/// request is recorded so tests can check its method, query and body with `received`.
+/// Requests to unknown paths get a 404 response. The server shuts down when it is dropped.
+pub struct StubServer {
+    url: String,
+    state: Arc<Mutex<StubState>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl StubServer {
+    /// Start a server listening on a random local port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no local port can be bound.
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle(&state, request).await) }
+                }))
+            }
+        });
+
+        let server = Server::try_bind(&([127, 0, 0, 1], 0).into())
+            .expect("could not bind stub server")
+            .serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            shutdown_rx.await.ok();
+        }));
+
+        Self {
+            url,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Base url of the server, suitable for use as an `api_server_host`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Create an `EvgClient` talking to this server.
+    pub fn client(&self) -> EvgClient {
+        EvgClient::builder(&self.url, "stub_user", "stub_key")
+            .build()
+            .expect("stub client configuration is valid")
+    }
+
+    /// Respond to requests for `path` with the given JSON document.
+    pub fn add_json(&self, path: &str, body: &Value) {
+        self.add_route(path, StubResponse::Json(body.to_string()));
+    }
+
+    /// Respond to requests for `path` with the given JSON fixture text.
+    pub fn add_json_str(&self, path: &str, body: &str) {
+        self.add_route(path, StubResponse::Json(body.to_string()));
+    }
+
+    /// Serve `items` from `path` as JSON arrays of `page_size` items, linking each page to the
+    /// next one through the `Link` header. The link keeps the query of the request, so filters
+    /// apply to every page.
+    pub fn add_pages(&self, path: &str, items: &[Value], page_size: usize) {
+        let pages = if items.is_empty() {
+            vec![String::from("[]")]
+        } else {
+            items
+                .chunks(page_size.max(1))
+                .map(|page| Value::from(page.to_vec()).to_string())
+                .collect()
+        };
+        self.add_route(path, StubResponse::Pages(pages));
+    }
+
+    /// Respond to requests for `path` with the given plain text, such as a log.
+    pub fn add_text(&self, path: &str, body: &str) {
+        self.add_route(path, StubResponse::Text(body.to_string()));
+    }
+
+    /// Respond to requests for `path` with the given status code and body.
+    pub fn add_status(&self, path: &str, status: u16, body: &str) {
+        let status = StatusCode::from_u16(status).expect("invalid status code");
+        self.add_route(path, StubResponse::Status(status, body.to_string()));
+    }
+
+    /// Respond to the next `times` requests for `path` with `429 Too Many Requests`, including a
+    /// `Retry-After` header if `retry_after` seconds are given.
+    pub fn throttle(&self, path: &str, times: usize, retry_after: Option<u64>) {
+        self.state().throttles.insert(
+            path.to_string(),
+            Throttle {
+                remaining: times,
+                retry_after,
+            },
+        );
+    }
+
+    /// Number of requests received for `path`.
+    pub fn requests(&self, path: &str) -> usize {
+        self.received(path).len()
+    }
+
+    /// Requests received for `path`, oldest first.
+    pub fn received(&self, path: &str) -> Vec<RecordedRequest> {
+        self.state()
+            .received
+            .iter()
+            .filter(|request| request.path == path)
+            .cloned()
+            .collect()
+    }
+
+    /// The latest request received for `path`.
+    pub fn last_request(&self, path: &str) -> Option<RecordedRequest> {
+        self.received(path).pop()
+    }
+
+    fn add_route(&self, path: &str, response: StubResponse) {
+        self.state().routes.insert(path.to_string(), response);
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, StubState> {
+        self.state.lock().expect("stub server lock poisoned")
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+/// Build the response to the given request from the configured routes.
+async fn handle(state: &Mutex<StubState>, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path().to_string();
+    let query: Vec<(String, String)> =
+        serde_urlencoded::from_str(request.uri().query().unwrap_or_default()).unwrap_or_default();
+    let page = query
+        .iter()
+        .find(|(key, _)| key == PAGE_PARAM)
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let host = request
+        .headers()
+        .get(hyper::header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost")
+        .to_string();
+    let method = request.method().clone();
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+
+    let mut state = state.lock().expect("stub server lock poisoned");
+    state.received.push(RecordedRequest {
+        method,
+        path: path.clone(),
+        query: query.clone(),
+        body,
+    });
+
+    if let Some(throttle) = state.throttles.get_mut(&path) {
+        if throttle.remaining > 0 {
+            throttle.remaining -= 1;
+            let mut response = Response::builder().status(StatusCode::TOO_MANY_REQUESTS);
+            if let Some(retry_after) = throttle.retry_after {
+                response = response.header(RETRY_AFTER, retry_after.to_string());
+            }
+            return response.body(Body::from("rate limit exceeded")).unwrap();
+        }
+    }
+
+    match state.routes.get(&path) {
+        Some(StubResponse::Json(body)) => json_response(body.clone()),
+        Some(StubResponse::Pages(pages)) => match pages.get(page) {
+            Some(body) => {
+                let mut response = json_response(body.clone());
+                if page + 1 < pages.len() {
+                    let mut next_query: Vec<(String, String)> = query
+                        .iter()
+                        .filter(|(key, _)| key != PAGE_PARAM)
+                        .cloned()
+                        .collect();
+                    next_query.push((PAGE_PARAM.to_string(), (page + 1).to_string()));
+                    let next = format!(
+                        "<http://{}{}?{}>; rel=\"next\"",
+                        host,
+                        path,
+                        serde_urlencoded::to_string(&next_query).expect("query can be encoded")
+                    );
+                    response
+                        .headers_mut()
+                        .insert(LINK, next.parse().expect("link header is valid"));
+                }
+                response
+            }
+            None => not_found(),
+        },
+        Some(StubResponse::Text(body)) => Response::new(Body::from(body.clone())),
+        Some(StubResponse::Status(status, body)) => Response::builder()
+            .status(*status)
+            .body(Body::from(body.clone()))
+            .unwrap(),
+        None => not_found(),
+    }
+}
+
+fn json_response(body: String) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("not found"))
+        .unwrap()
+}
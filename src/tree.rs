@@ -0,0 +1,284 @@
+use crate::models::build::EvgBuild;
+use crate::models::ids::{BuildId, TaskId, VersionId};
+use crate::models::task::EvgTask;
+use crate::models::test::EvgTest;
+use crate::models::version::EvgVersion;
+use crate::{EvgApiClient, EvgError};
+use futures::stream::{self, StreamExt};
+use std::future::Future;
+use tokio::sync::Semaphore;
+
+/// How far below the version a tree is fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TreeDepth {
+    /// Only the builds of the version.
+    Builds,
+    /// The builds and their tasks.
+    Tasks,
+    /// The builds, their tasks and the tests of each task.
+    Tests,
+}
+
+/// A task of a version tree, along with its tests if they were fetched.
+#[derive(Debug, Clone)]
+pub struct TaskNode {
+    pub task: EvgTask,
+    pub tests: Vec<EvgTest>,
+}
+
+/// A build of a version tree, along with its tasks if they were fetched.
+#[derive(Debug, Clone)]
+pub struct BuildNode {
+    pub build: EvgBuild,
+    pub tasks: Vec<TaskNode>,
+}
+
+/// A part of a version tree that could not be fetched.
+#[derive(Debug)]
+pub enum TreeFetchFailure {
+    /// The build could not be fetched, so it is missing from the tree.
+    Build { build_id: BuildId, error: EvgError },
+    /// The tasks of the build could not be listed; the tasks listed before the error are kept.
+    Tasks { build_id: BuildId, error: EvgError },
+    /// The tests of the task could not be fetched, so the task has no tests.
+    Tests { task_id: TaskId, error: EvgError },
+}
+
+/// A version along with its builds, tasks and tests.
+#[derive(Debug)]
+pub struct VersionTree {
+    pub version: EvgVersion,
+    pub builds: Vec<BuildNode>,
+    /// Parts of the tree that could not be fetched.
+    pub failures: Vec<TreeFetchFailure>,
+}
+
+impl VersionTree {
+    /// Check if every part of the tree was fetched.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Tasks of all builds.
+    pub fn tasks(&self) -> impl Iterator<Item = &EvgTask> {
+        self.builds
+            .iter()
+            .flat_map(|build| build.tasks.iter().map(|node| &node.task))
+    }
+
+    /// Tests of all tasks.
+    pub fn tests(&self) -> impl Iterator<Item = &EvgTest> {
+        self.builds
+            .iter()
+            .flat_map(|build| build.tasks.iter())
+            .flat_map(|node| node.tests.iter())
+    }
+}
+
+/// Fetch the given version and everything below it down to `depth`, with at most
+/// `concurrency` requests in flight.
+///
+/// Only a failure to fetch the version itself is returned as an error. Other failures are
+/// reported in the tree and leave the affected part out.
+pub(crate) async fn fetch<C>(
+    client: &C,
+    version_id: &VersionId,
+    depth: TreeDepth,
+    concurrency: usize,
+) -> Result<VersionTree, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+{
+    let concurrency = concurrency.max(1);
+    let version = client.get_version(version_id).await?;
+    let build_ids: Vec<BuildId> = version
+        .build_variants_status
+        .iter()
+        .flatten()
+        .map(|b| b.build_id.clone())
+        .collect();
+
+    let fetcher = TreeFetcher {
+        client,
+        depth,
+        concurrency,
+        permits: Semaphore::new(concurrency),
+    };
+    let results: Vec<(Option<BuildNode>, Vec<TreeFetchFailure>)> = stream::iter(build_ids)
+        .map(|build_id| fetcher.build_node(build_id))
+        .buffered(concurrency)
+        .collect()
+        .await;
+
+    let mut builds = vec![];
+    let mut failures = vec![];
+    for (build, build_failures) in results {
+        builds.extend(build);
+        failures.extend(build_failures);
+    }
+    Ok(VersionTree {
+        version,
+        builds,
+        failures,
+    })
+}
+
+struct TreeFetcher<'a, C: ?Sized> {
+    client: &'a C,
+    depth: TreeDepth,
+    concurrency: usize,
+    permits: Semaphore,
+}
+
+impl<'a, C: EvgApiClient + ?Sized> TreeFetcher<'a, C> {
+    /// Fetch the given build and everything below it, along with what could not be fetched.
+    async fn build_node(&self, build_id: BuildId) -> (Option<BuildNode>, Vec<TreeFetchFailure>) {
+        let mut failures = vec![];
+        let build = match self.limited(self.client.get_build(&build_id)).await {
+            Ok(Some(build)) => build,
+            Ok(None) => {
+                let error = EvgError::NotFound {
+                    url: format!("builds/{}", build_id),
+                };
+                failures.push(TreeFetchFailure::Build { build_id, error });
+                return (None, failures);
+            }
+            Err(error) => {
+                failures.push(TreeFetchFailure::Build { build_id, error });
+                return (None, failures);
+            }
+        };
+        if self.depth == TreeDepth::Builds {
+            return (
+                Some(BuildNode {
+                    build,
+                    tasks: vec![],
+                }),
+                failures,
+            );
+        }
+
+        let mut tasks = vec![];
+        {
+            let _permit = self.permit().await;
+            let mut stream = self.client.stream_build_tasks(&build_id, None);
+            while let Some(task) = stream.next().await {
+                match task {
+                    Ok(task) => tasks.push(task),
+                    Err(error) => {
+                        failures.push(TreeFetchFailure::Tasks { build_id, error });
+                        break;
+                    }
+                }
+            }
+        }
+
+        let tasks = if self.depth == TreeDepth::Tests {
+            let results: Vec<(EvgTask, Result<Vec<EvgTest>, EvgError>)> = stream::iter(tasks)
+                .map(|task| async move {
+                    let tests = self.limited(self.client.get_tests(&task.task_id)).await;
+                    (task, tests)
+                })
+                .buffered(self.concurrency)
+                .collect()
+                .await;
+            results
+                .into_iter()
+                .map(|(task, tests)| {
+                    let tests = tests.unwrap_or_else(|error| {
+                        let task_id = task.task_id.clone();
+                        failures.push(TreeFetchFailure::Tests { task_id, error });
+                        vec![]
+                    });
+                    TaskNode { task, tests }
+                })
+                .collect()
+        } else {
+            tasks
+                .into_iter()
+                .map(|task| TaskNode {
+                    task,
+                    tests: vec![],
+                })
+                .collect()
+        };
+        (Some(BuildNode { build, tasks }), failures)
+    }
+
+    /// Run the given request once a permit is available.
+    async fn limited<T>(&self, request: impl Future<Output = T>) -> T {
+        let _permit = self.permit().await;
+        request.await
+    }
+
+    async fn permit(&self) -> tokio::sync::SemaphorePermit<'_> {
+        self.permits
+            .acquire()
+            .await
+            .expect("tree fetch semaphore is never closed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::{self, version_json};
+    use crate::testing::{FakeEvgClient, FakeMethod};
+
+    fn client() -> FakeEvgClient {
+        let client = FakeEvgClient::new();
+        let mut version = version_json("version", 1);
+        version["build_variants_status"] = serde_json::json!([
+            { "build_variant": "build_0", "build_id": "build_0" },
+            { "build_variant": "build_1", "build_id": "build_1" },
+        ]);
+        client.insert_version(fixtures::parse(version));
+        client.insert_build(fixtures::build("build_0", "version", "failed"));
+        for (task_id, status) in &[("task_0", "success"), ("task_1", "failed")] {
+            client.insert_task(fixtures::task(task_id, "build_0", status));
+            client.insert_test(fixtures::test(task_id, "test", "pass"));
+        }
+        client
+    }
+
+    #[tokio::test]
+    async fn test_fetch_version_tree_should_report_missing_builds() {
+        let client = client();
+
+        let tree = client
+            .fetch_version_tree(&"version".into(), TreeDepth::Tests, 4)
+            .await
+            .unwrap();
+
+        assert_eq!(tree.builds.len(), 1);
+        assert_eq!(tree.tasks().count(), 2);
+        assert_eq!(tree.tests().count(), 2);
+        assert!(matches!(
+            &tree.failures[..],
+            [TreeFetchFailure::Build { build_id, .. }] if build_id == "build_1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_version_tree_should_keep_tasks_when_tests_fail() {
+        let client = client();
+        client.fail_with(FakeMethod::GetTests, || EvgError::NotFound {
+            url: "tests".to_string(),
+        });
+
+        let shallow = client
+            .fetch_version_tree(&"version".into(), TreeDepth::Tasks, 1)
+            .await
+            .unwrap();
+        let deep = client
+            .fetch_version_tree(&"version".into(), TreeDepth::Tests, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(shallow.tasks().count(), 2);
+        assert_eq!(shallow.failures.len(), 1);
+        assert_eq!(deep.tasks().count(), 2);
+        assert_eq!(deep.tests().count(), 0);
+        assert_eq!(deep.failures.len(), 3);
+    }
+}
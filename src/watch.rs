@@ -0,0 +1,636 @@
+use crate::models::build::{BuildStatusCounts, EvgBuild};
+use crate::models::commit_queue::EvgCommitQueueItem;
+use crate::models::ids::{BuildId, PatchId, ProjectId, TaskId, VersionId};
+use crate::models::patch::EvgPatch;
+use crate::models::task::EvgTask;
+use crate::models::version::EvgVersion;
+use crate::{EvgApiClient, EvgError, EvgStream};
+use async_stream::try_stream;
+use chrono::Utc;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Share of its expected duration a running task can report before it finishes.
+const MAX_RUNNING_TASK_PROGRESS: f64 = 0.99;
+
+/// Upper bound on the delay between commit queue polls after consecutive failures.
+const MAX_WATCH_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Maximum number of builds fetched at the same time to compute the progress of a version.
+const VERSION_PROGRESS_CONCURRENCY: usize = 8;
+
+/// How often an object is polled while waiting for it to finish.
+#[derive(Debug, Clone)]
+pub struct WaitOptions {
+    /// Delay between the first two polls, doubled after every subsequent poll.
+    pub base_interval: Duration,
+    /// Upper bound on the delay between polls.
+    pub max_interval: Duration,
+    /// Give up with `EvgError::WaitTimeout` once this much time has passed. Without a timeout,
+    /// a wait keeps polling through transient errors for as long as the object is unfinished.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        Self {
+            base_interval: Duration::from_secs(5),
+            max_interval: Duration::from_secs(60),
+            timeout: None,
+        }
+    }
+}
+
+/// A snapshot of an object being waited for.
+#[derive(Debug, Clone)]
+pub struct WaitProgress<T> {
+    /// The object as of the latest poll.
+    pub object: T,
+    /// Fraction of the work done, between 0.0 and 1.0.
+    pub percent_complete: f64,
+    /// Whether the object reached a terminal status. This is the last snapshot if set.
+    pub finished: bool,
+    /// Builds of the version that could not be found, so their tasks are not counted in
+    /// `percent_complete`.
+    pub missing_builds: Vec<BuildId>,
+    /// Time since the wait started.
+    pub elapsed: Duration,
+}
+
+/// The state of an object as read by a single poll.
+struct PollResult<T> {
+    object: T,
+    percent_complete: f64,
+    finished: bool,
+    missing_builds: Vec<BuildId>,
+}
+
+impl<T> PollResult<T> {
+    fn new(object: T, percent_complete: f64, finished: bool) -> Self {
+        Self {
+            object,
+            percent_complete,
+            finished,
+            missing_builds: vec![],
+        }
+    }
+}
+
+/// Progress of a version, computed over the tasks of its builds.
+struct VersionProgress {
+    percent_complete: f64,
+    missing_builds: Vec<BuildId>,
+}
+
+/// A change to a commit queue between two polls.
+#[derive(Debug, Clone)]
+pub enum CommitQueueChange {
+    /// An item was added at the given zero-based position.
+    Enqueued {
+        item: EvgCommitQueueItem,
+        position: usize,
+    },
+    /// An item left the queue, either merged or removed.
+    Dequeued { item: EvgCommitQueueItem },
+    /// An item moved, usually because items ahead of it left the queue.
+    Moved {
+        item: EvgCommitQueueItem,
+        from: usize,
+        to: usize,
+    },
+    /// Polling failed with a transient error and will be retried after `retry_in`.
+    PollFailed {
+        error: Arc<EvgError>,
+        retry_in: Duration,
+    },
+}
+
+/// Poll the commit queue of the given project every `interval`, yielding its changes.
+///
+/// The first poll reports every queued item as enqueued. Transient poll errors are reported as
+/// `CommitQueueChange::PollFailed` and retried with exponential backoff. The stream never ends
+/// on its own, except after yielding any other error.
+pub fn watch_commit_queue<C>(
+    client: Arc<C>,
+    project_id: ProjectId,
+    interval: Duration,
+) -> EvgStream<CommitQueueChange>
+where
+    C: EvgApiClient + ?Sized + 'static,
+{
+    Box::pin(try_stream! {
+        let mut previous: Vec<EvgCommitQueueItem> = vec![];
+        let mut failures: u32 = 0;
+        loop {
+            let delay = match client.get_commit_queue(&project_id).await {
+                Err(error) if error.is_transient() => {
+                    failures += 1;
+                    let retry_in = watch_backoff(interval, failures);
+                    yield CommitQueueChange::PollFailed {
+                        error: Arc::new(error),
+                        retry_in,
+                    };
+                    retry_in
+                }
+                result => {
+                    let current = result?.items().to_vec();
+                    for change in diff_queues(&previous, &current) {
+                        yield change;
+                    }
+                    previous = current;
+                    failures = 0;
+                    interval
+                }
+            };
+            tokio::time::sleep(delay).await;
+        }
+    })
+}
+
+/// Delay before polling again after the given number of consecutive failures.
+fn watch_backoff(interval: Duration, failures: u32) -> Duration {
+    let exponent = failures.min(31);
+    interval
+        .checked_mul(1 << exponent)
+        .unwrap_or(MAX_WATCH_BACKOFF)
+        .min(MAX_WATCH_BACKOFF.max(interval))
+}
+
+/// Poll the given task until it finishes, yielding a snapshot after every poll.
+///
+/// Progress is estimated from the expected duration of the task while it runs.
+pub fn wait_for_task<C>(
+    client: Arc<C>,
+    task_id: TaskId,
+    options: WaitOptions,
+) -> EvgStream<WaitProgress<EvgTask>>
+where
+    C: EvgApiClient + ?Sized + 'static,
+{
+    poll_until_finished(options, move || {
+        let client = client.clone();
+        let task_id = task_id.clone();
+        async move {
+            let task = client.get_task(&task_id).await?;
+            let finished = task.status.is_finished();
+            let progress = if finished { 1.0 } else { task_progress(&task) };
+            Ok(PollResult::new(task, progress, finished))
+        }
+    })
+}
+
+/// Poll the given build until it finishes, yielding a snapshot after every poll.
+pub fn wait_for_build<C>(
+    client: Arc<C>,
+    build_id: BuildId,
+    options: WaitOptions,
+) -> EvgStream<WaitProgress<EvgBuild>>
+where
+    C: EvgApiClient + ?Sized + 'static,
+{
+    poll_until_finished(options, move || {
+        let client = client.clone();
+        let build_id = build_id.clone();
+        async move {
+            let build = client
+                .get_build(&build_id)
+                .await?
+                .ok_or_else(|| EvgError::NotFound {
+                    url: format!("builds/{}", build_id),
+                })?;
+            let finished = build.status.is_finished();
+            let progress = if finished {
+                1.0
+            } else {
+                build.status_counts.percent_complete()
+            };
+            Ok(PollResult::new(build, progress, finished))
+        }
+    })
+}
+
+/// Poll the given version until it finishes, yielding a snapshot after every poll.
+///
+/// Progress is computed over the tasks of all builds of the version.
+pub fn wait_for_version<C>(
+    client: Arc<C>,
+    version_id: VersionId,
+    options: WaitOptions,
+) -> EvgStream<WaitProgress<EvgVersion>>
+where
+    C: EvgApiClient + ?Sized + 'static,
+{
+    poll_until_finished(options, move || {
+        let client = client.clone();
+        let version_id = version_id.clone();
+        async move {
+            let version = client.get_version(&version_id).await?;
+            if version.status.is_finished() {
+                return Ok(PollResult::new(version, 1.0, true));
+            }
+            let progress = version_progress(client.as_ref(), &version).await?;
+            Ok(PollResult {
+                object: version,
+                percent_complete: progress.percent_complete,
+                finished: false,
+                missing_builds: progress.missing_builds,
+            })
+        }
+    })
+}
+
+/// Poll the given patch until it finishes, yielding a snapshot after every poll.
+///
+/// Progress is computed over the tasks of the version of the patch once it is activated.
+pub fn wait_for_patch<C>(
+    client: Arc<C>,
+    patch_id: PatchId,
+    options: WaitOptions,
+) -> EvgStream<WaitProgress<EvgPatch>>
+where
+    C: EvgApiClient + ?Sized + 'static,
+{
+    poll_until_finished(options, move || {
+        let client = client.clone();
+        let patch_id = patch_id.clone();
+        async move {
+            let patch = client.get_patch(&patch_id).await?;
+            if patch.status.is_finished() {
+                return Ok(PollResult::new(patch, 1.0, true));
+            }
+            if !patch.activated || patch.version.as_str().is_empty() {
+                return Ok(PollResult::new(patch, 0.0, false));
+            }
+            let version = client.get_version(&patch.version).await?;
+            let progress = version_progress(client.as_ref(), &version).await?;
+            Ok(PollResult {
+                object: patch,
+                percent_complete: progress.percent_complete,
+                finished: false,
+                missing_builds: progress.missing_builds,
+            })
+        }
+    })
+}
+
+/// Call `poll` with exponential backoff until it reports the object as finished.
+///
+/// Transient errors are not reported; polling carries on until the timeout, if any. Other
+/// errors end the stream.
+fn poll_until_finished<T, F, Fut>(options: WaitOptions, poll: F) -> EvgStream<WaitProgress<T>>
+where
+    T: Send + 'static,
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<PollResult<T>, EvgError>> + Send,
+{
+    Box::pin(try_stream! {
+        let start = Instant::now();
+        let mut interval = options.base_interval;
+        loop {
+            match poll().await {
+                Err(err) if err.is_transient() => {}
+                result => {
+                    let result = result?;
+                    let finished = result.finished;
+                    yield WaitProgress {
+                        object: result.object,
+                        percent_complete: result.percent_complete,
+                        finished,
+                        missing_builds: result.missing_builds,
+                        elapsed: start.elapsed(),
+                    };
+                    if finished {
+                        break;
+                    }
+                }
+            }
+
+            let elapsed = start.elapsed();
+            let mut delay = interval.min(options.max_interval);
+            if let Some(timeout) = options.timeout {
+                let remaining = timeout.checked_sub(elapsed).unwrap_or_default();
+                if remaining.is_zero() {
+                    Err(EvgError::WaitTimeout { elapsed })?;
+                }
+                delay = delay.min(remaining);
+            }
+            tokio::time::sleep(delay).await;
+            interval = interval.saturating_mul(2).min(options.max_interval);
+        }
+    })
+}
+
+/// Estimate how far along an unfinished task is from its expected duration.
+fn task_progress(task: &EvgTask) -> f64 {
+    match task.start_time {
+        Some(start_time) if task.expected_duration_ms > 0 => {
+            let running_ms = (Utc::now() - start_time).num_milliseconds().max(0) as f64;
+            (running_ms / task.expected_duration_ms as f64).min(MAX_RUNNING_TASK_PROGRESS)
+        }
+        _ => 0.0,
+    }
+}
+
+/// Fraction of the tasks of the given version that are finished, along with the builds that
+/// could not be found.
+async fn version_progress<C>(client: &C, version: &EvgVersion) -> Result<VersionProgress, EvgError>
+where
+    C: EvgApiClient + ?Sized,
+{
+    let fetches: Vec<_> = version
+        .build_variants_status
+        .iter()
+        .flatten()
+        .map(|b| fetch_build(client, b.build_id.clone()))
+        .collect();
+    let builds: Vec<(BuildId, Option<EvgBuild>)> = stream::iter(fetches)
+        .buffer_unordered(VERSION_PROGRESS_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    let mut counts = BuildStatusCounts::new();
+    let mut missing_builds = vec![];
+    for (build_id, build) in builds {
+        match build {
+            Some(build) => counts.add(&build.status_counts),
+            None => missing_builds.push(build_id),
+        }
+    }
+    missing_builds.sort();
+    Ok(VersionProgress {
+        percent_complete: counts.percent_complete(),
+        missing_builds,
+    })
+}
+
+async fn fetch_build<C>(
+    client: &C,
+    build_id: BuildId,
+) -> Result<(BuildId, Option<EvgBuild>), EvgError>
+where
+    C: EvgApiClient + ?Sized,
+{
+    let build = client.get_build(&build_id).await?;
+    Ok((build_id, build))
+}
+
+/// Compute the changes turning the `previous` queue into the `current` one.
+fn diff_queues(
+    previous: &[EvgCommitQueueItem],
+    current: &[EvgCommitQueueItem],
+) -> Vec<CommitQueueChange> {
+    let position_in =
+        |items: &[EvgCommitQueueItem], issue: &str| items.iter().position(|i| i.issue == issue);
+
+    let mut changes: Vec<CommitQueueChange> = previous
+        .iter()
+        .filter(|item| position_in(current, &item.issue).is_none())
+        .map(|item| CommitQueueChange::Dequeued { item: item.clone() })
+        .collect();
+    for (position, item) in current.iter().enumerate() {
+        match position_in(previous, &item.issue) {
+            None => changes.push(CommitQueueChange::Enqueued {
+                item: item.clone(),
+                position,
+            }),
+            Some(from) if from != position => changes.push(CommitQueueChange::Moved {
+                item: item.clone(),
+                from,
+                to: position,
+            }),
+            Some(_) => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::{self, build_json, version_json};
+    use crate::testing::{FakeEvgClient, FakeMethod};
+    use futures::StreamExt;
+    use reqwest::StatusCode;
+
+    fn options(timeout: Option<Duration>) -> WaitOptions {
+        WaitOptions {
+            base_interval: Duration::from_millis(5),
+            max_interval: Duration::from_millis(20),
+            timeout,
+        }
+    }
+
+    fn item(issue: &str) -> EvgCommitQueueItem {
+        EvgCommitQueueItem {
+            issue: issue.to_string(),
+            version: None,
+            enqueue_time: None,
+            source: None,
+            patch: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_commit_queue_should_report_changes_between_polls() {
+        let client = Arc::new(FakeEvgClient::new());
+        let project_id = ProjectId::from("project");
+        client.set_commit_queue(&project_id, vec![item("a"), item("b")]);
+        let mut changes = watch_commit_queue(
+            client.clone(),
+            project_id.clone(),
+            Duration::from_millis(10),
+        );
+
+        let initial: Vec<CommitQueueChange> = vec![
+            changes.next().await.unwrap().unwrap(),
+            changes.next().await.unwrap().unwrap(),
+        ];
+        client.set_commit_queue(&project_id, vec![item("b"), item("c")]);
+        let merged = changes.next().await.unwrap().unwrap();
+        let moved = changes.next().await.unwrap().unwrap();
+        let enqueued = changes.next().await.unwrap().unwrap();
+
+        assert!(initial
+            .iter()
+            .all(|c| matches!(c, CommitQueueChange::Enqueued { .. })));
+        assert!(matches!(merged, CommitQueueChange::Dequeued { item } if item.issue == "a"));
+        assert!(matches!(
+            moved,
+            CommitQueueChange::Moved { from: 1, to: 0, .. }
+        ));
+        assert!(matches!(
+            enqueued,
+            CommitQueueChange::Enqueued { position: 1, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_watch_commit_queue_should_report_transient_errors_and_recover() {
+        let client = Arc::new(FakeEvgClient::new());
+        let project_id = ProjectId::from("project");
+        client.set_commit_queue(&project_id, vec![item("a")]);
+        client.fail_with(FakeMethod::GetCommitQueue, || EvgError::HttpStatus {
+            url: "commit_queue/project".to_string(),
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            body: String::new(),
+        });
+        let mut changes = watch_commit_queue(client.clone(), project_id, Duration::from_millis(5));
+
+        let failed = changes.next().await.unwrap().unwrap();
+        client.clear_failure(FakeMethod::GetCommitQueue);
+        let enqueued = changes.next().await.unwrap().unwrap();
+
+        assert!(matches!(
+            failed,
+            CommitQueueChange::PollFailed { retry_in, .. } if retry_in == Duration::from_millis(10)
+        ));
+        assert!(matches!(
+            enqueued,
+            CommitQueueChange::Enqueued { position: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_watch_backoff_should_double_until_max() {
+        let interval = Duration::from_secs(10);
+
+        assert_eq!(watch_backoff(interval, 1), Duration::from_secs(20));
+        assert_eq!(watch_backoff(interval, 40), MAX_WATCH_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_build_should_report_progress_until_finished() {
+        let client = Arc::new(FakeEvgClient::new());
+        let mut build = build_json("build", "version", "started");
+        build["status_counts"]["succeeded"] = serde_json::json!(1);
+        build["status_counts"]["started"] = serde_json::json!(3);
+        client.insert_build(fixtures::parse(build.clone()));
+        let mut progress = wait_for_build(client.clone(), "build".into(), options(None));
+
+        let running = progress.next().await.unwrap().unwrap();
+        build["status"] = serde_json::json!("success");
+        client.insert_build(fixtures::parse(build));
+        let done = progress.next().await.unwrap().unwrap();
+
+        assert!(!running.finished);
+        assert_eq!(running.percent_complete, 0.25);
+        assert!(done.finished);
+        assert_eq!(done.percent_complete, 1.0);
+        assert!(progress.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_patch_should_finish_when_patch_succeeds() {
+        let client = Arc::new(FakeEvgClient::new());
+        client.insert_patch(fixtures::patch("patch", "created"));
+        let mut progress = wait_for_patch(client.clone(), "patch".into(), options(None));
+
+        let created = progress.next().await.unwrap().unwrap();
+        client.insert_patch(fixtures::patch("patch", "succeeded"));
+        let done = progress.next().await.unwrap().unwrap();
+
+        assert!(!created.finished);
+        assert!(done.finished);
+        assert_eq!(done.percent_complete, 1.0);
+        assert!(progress.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_version_should_report_missing_builds() {
+        let client = Arc::new(FakeEvgClient::new());
+        let mut version = version_json("version", 1);
+        version["status"] = serde_json::json!("started");
+        version["build_variants_status"] = serde_json::json!([
+            { "build_variant": "build_0", "build_id": "build_0" },
+            { "build_variant": "build_1", "build_id": "build_1" },
+        ]);
+        client.insert_version(fixtures::parse(version));
+        let mut build = build_json("build_0", "version", "started");
+        build["status_counts"]["succeeded"] = serde_json::json!(1);
+        build["status_counts"]["undispatched"] = serde_json::json!(1);
+        client.insert_build(fixtures::parse(build));
+
+        let progress = wait_for_version(client, "version".into(), options(None))
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!progress.finished);
+        assert_eq!(progress.percent_complete, 0.5);
+        assert_eq!(progress.missing_builds, vec!["build_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_task_should_keep_polling_after_transient_errors() {
+        let client = Arc::new(FakeEvgClient::new());
+        client.insert_task(fixtures::task("task", "build", "success"));
+        client.fail_with(FakeMethod::GetTask, || EvgError::HttpStatus {
+            url: "tasks/task".to_string(),
+            status: StatusCode::BAD_GATEWAY,
+            body: String::new(),
+        });
+        let mut progress = wait_for_task(client.clone(), "task".into(), options(None));
+        let recover = async {
+            tokio::time::sleep(Duration::from_millis(15)).await;
+            client.clear_failure(FakeMethod::GetTask);
+        };
+
+        let (first, _) = tokio::join!(progress.next(), recover);
+
+        assert!(first.unwrap().unwrap().finished);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_task_should_end_on_other_errors() {
+        let client = Arc::new(FakeEvgClient::new());
+
+        let results: Vec<Result<WaitProgress<EvgTask>, EvgError>> =
+            wait_for_task(client, "missing".into(), options(None))
+                .collect()
+                .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].as_ref().unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_task_should_end_on_request_build_errors() {
+        let client = Arc::new(FakeEvgClient::new());
+        client.insert_task(fixtures::task("task", "build", "started"));
+        client.fail_with(FakeMethod::GetTask, || {
+            let err = reqwest::Client::new().get("no-scheme").build().unwrap_err();
+            EvgError::Transport(err)
+        });
+
+        let results: Vec<Result<WaitProgress<EvgTask>, EvgError>> =
+            wait_for_task(client, "task".into(), options(None))
+                .collect()
+                .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(EvgError::Transport(_))));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_task_should_time_out() {
+        let client = Arc::new(FakeEvgClient::new());
+        client.insert_task(fixtures::task("task", "build", "started"));
+
+        let results: Vec<Result<WaitProgress<EvgTask>, EvgError>> = wait_for_task(
+            client,
+            "task".into(),
+            options(Some(Duration::from_millis(30))),
+        )
+        .collect()
+        .await;
+
+        assert!(results.len() > 1);
+        assert!(matches!(
+            results.last(),
+            Some(Err(EvgError::WaitTimeout { .. }))
+        ));
+    }
+}